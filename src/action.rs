@@ -1,26 +1,67 @@
 use rspotify::model::{
-    CurrentPlaybackContext, FullTrack, SavedTrack, SimplifiedPlaylist,
+    CurrentPlaybackContext, FullTrack, RepeatState, SavedTrack, SimplifiedPlaylist,
 };
 
+use crate::error::AppError;
+use crate::history::{Period, Play, Stats};
+
 /// Events sent from the event handler to the main loop.
 #[derive(Debug)]
 pub enum Event {
     Key(crossterm::event::KeyEvent),
+    Mouse(crossterm::event::MouseEvent),
     Tick,
-    Resize(u16, u16),
+    Resize,
+    FocusGained,
+    FocusLost,
+    Remote(Remote),
+}
+
+/// Playback requests from outside the TUI, like desktop media keys.
+#[derive(Debug, Clone)]
+pub enum Remote {
+    PlayPause,
+    Play,
+    Pause,
+    Next,
+    Previous,
+    Seek(i64),        // offset in milliseconds
+    SetPosition(i64), // position in milliseconds
+    SetVolume(u8),
+    ChangeVolume(i32), // percentage points up or down
+    SetShuffle(bool),
+    SetRepeat(RepeatState),
+    SetLiked(bool), // the track playing
+    ToggleLiked,
+    /// A party guest, by address, suggesting a track or voting for one.
+    Suggest {
+        guest: String,
+        track: Box<FullTrack>,
+    },
+    Vote {
+        guest: String,
+        id: u64,
+    },
 }
 
 /// IO requests sent from the app to the network handler.
 #[derive(Debug)]
 pub enum IoEvent {
+    LoadCachedLibrary,
     FetchNowPlaying,
-    PlayPause,
+    Pause,
+    Resume,
     NextTrack,
     PreviousTrack,
-    VolumeUp,
-    VolumeDown,
+    SetVolume(u8),
+    Seek(i64), // position in milliseconds
+    SetShuffle(bool),
+    SetRepeat(RepeatState),
     FetchPlaylists,
-    FetchPlaylistTracks(String), // playlist ID
+    FetchPlaylistTracks {
+        playlist_id: String,
+        snapshot_id: String,
+    },
     PlayTrackInContext {
         context_uri: String,
         offset: usize,
@@ -30,15 +71,35 @@ pub enum IoEvent {
     FetchLikedSongs,
     ToggleLike {
         track_id: String,
+        /// The track being liked, if the app has it, for the library cache
+        track: Option<Box<FullTrack>>,
         currently_liked: bool,
     },
     FetchDevices,
+    TransferPlayback(String), // device ID
+    AddToPlaylist {
+        playlist_id: String,
+        track_id: String,
+    },
+    AddToQueue {
+        track_uri: String,
+        name: String,
+    },
+    RecordPlay(Box<Play>),
+    LoadStats(Period),
+    RefreshToken,
+    Reauthenticate,
+    SwitchProfile(String),
 }
 
 /// Actions dispatched to update App state.
 #[derive(Debug)]
 pub enum Action {
-    NowPlayingUpdated(Option<CurrentPlaybackContext>),
+    CachedLibraryLoaded {
+        playlists: Vec<SimplifiedPlaylist>,
+        liked_songs: Vec<SavedTrack>,
+    },
+    NowPlayingUpdated(Option<Box<CurrentPlaybackContext>>),
     PlaylistsLoaded(Vec<SimplifiedPlaylist>),
     PlaylistTracksLoaded(Vec<FullTrack>),
     SearchResultsLoaded {
@@ -49,6 +110,30 @@ pub enum Action {
         track_id: String,
         is_liked: bool,
     },
-    Error(String),
+    Error {
+        context: String,
+        error: AppError,
+    },
+    PlaybackCommandSucceeded,
+    PlaybackCommandFailed {
+        context: String,
+        error: AppError,
+    },
+    AddedToPlaylist {
+        playlist_id: String,
+        snapshot_id: String,
+    },
+    AddedToQueue(String), // track name
     DevicesLoaded(Vec<rspotify::model::Device>),
+    PlayRecorded,
+    StatsLoaded(Box<Stats>),
+    /// A browser login is waiting on the user, at this URL.
+    LoginStarted(String),
+    Authenticated,
+    /// Refreshing the token, logging in again or switching accounts failed.
+    AuthenticationFailed {
+        context: String,
+        error: AppError,
+    },
+    ProfileSwitched(String),
 }
@@ -1,20 +1,47 @@
 use rspotify::model::{
-    CurrentPlaybackContext, Device, FullTrack, PlayableItem, SavedTrack, SimplifiedPlaylist,
+    CurrentPlaybackContext, Device, FullTrack, PlayableItem, RepeatState, SavedTrack,
+    SimplifiedPlaylist,
 };
+use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
-use crate::action::{Action, IoEvent};
+use crate::action::{Action, IoEvent, Remote};
+use crate::config::Settings;
+use crate::cli;
+use crate::error::AppError;
+use crate::history::{Period, Play, PlayTracker, Ranked, Stats};
+use crate::hooks::{self, Hook};
+use crate::keymap::{Command, Key, KeyMatch};
+use crate::palette::{self, Entry, Palette, Prompt};
+use crate::party::{PartyQueue, Suggestion};
+use crate::scrobble::{ListenTracker, Scrobble};
+use crate::ui::{devices, help, hit_test, party, profiles};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Spotify takes a moment to reflect playback commands in its reported state
+const RECONCILE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+// How much longer to wait between now playing polls when paused or idle,
+// relative to the configured interval
+const PAUSED_POLL_FACTOR: u32 = 3;
+const IDLE_POLL_FACTOR: u32 = 6;
+// Two clicks on the same cell within this window make a double-click
+const DOUBLE_CLICK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(400);
+// How long after the predicted end of a track to poll for the next one
+const TRACK_END_GRACE: std::time::Duration = std::time::Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Screen {
     Library,
     Search,
     LikedSongs,
+    Stats,
 }
 
 impl Screen {
     pub fn all() -> &'static [Screen] {
-        &[Screen::Library, Screen::Search, Screen::LikedSongs]
+        &[Screen::Library, Screen::Search, Screen::LikedSongs, Screen::Stats]
     }
 
     pub fn label(&self) -> &str {
@@ -22,6 +49,7 @@ impl Screen {
             Screen::Library => "Library",
             Screen::Search => "Search",
             Screen::LikedSongs => "Liked Songs",
+            Screen::Stats => "Stats",
         }
     }
 
@@ -29,15 +57,17 @@ impl Screen {
         match self {
             Screen::Library => Screen::Search,
             Screen::Search => Screen::LikedSongs,
-            Screen::LikedSongs => Screen::Library,
+            Screen::LikedSongs => Screen::Stats,
+            Screen::Stats => Screen::Library,
         }
     }
 
     pub fn prev(&self) -> Screen {
         match self {
-            Screen::Library => Screen::LikedSongs,
+            Screen::Library => Screen::Stats,
             Screen::Search => Screen::Library,
             Screen::LikedSongs => Screen::Search,
+            Screen::Stats => Screen::LikedSongs,
         }
     }
 }
@@ -56,10 +86,14 @@ pub enum Panel {
 
 pub struct App {
     pub running: bool,
+    pub settings: Settings,
     pub screen: Screen,
     pub input_mode: InputMode,
     pub active_panel: Panel,
     pub show_help: bool,
+    /// The selected row of the help overlay, which scrolls.
+    pub help_index: usize,
+    pub palette: Option<Palette>,
 
     // Now playing
     pub now_playing: Option<CurrentPlaybackContext>,
@@ -69,65 +103,293 @@ pub struct App {
     // Library
     pub playlists: Vec<SimplifiedPlaylist>,
     pub playlist_index: usize,
+    pub playlist_offset: usize,
     pub playlist_tracks: Vec<FullTrack>,
     pub track_index: usize,
+    pub track_offset: usize,
     pub selected_playlist_id: Option<String>,
 
     // Search
     pub search_input: String,
     pub search_results: Vec<FullTrack>,
     pub search_index: usize,
+    pub search_offset: usize,
 
     // Liked songs
     pub liked_songs: Vec<SavedTrack>,
     pub liked_index: usize,
+    pub liked_offset: usize,
     pub liked_track_ids: std::collections::HashSet<String>,
+    liked_songs_revalidated: bool,
+
+    // Listening stats
+    pub stats: Option<Stats>,
+    pub stats_period: Period,
+    pub stats_index: usize,
+    pub stats_offset: usize,
 
     // Devices
     pub devices: Vec<Device>,
+    pub show_devices: bool,
+    pub device_index: usize,
+
+    // Account profiles
+    pub profile: String,
+    pub profiles: Vec<String>,
+    pub show_profiles: bool,
+    /// The URL of a browser login in progress, until it's done or hidden.
+    pub login_url: Option<String>,
+    pub profile_index: usize,
+
+    // Guest suggestions from the web remote
+    pub party: PartyQueue,
+    pub show_party: bool,
+    pub party_index: usize,
+
+    // Keys typed so far towards a multi-key binding
+    pending_keys: Vec<Key>,
+    // Count typed before a motion, like the 5 in `5j`
+    count: Option<usize>,
+
+    // The terminal area as of the last draw, for paging through lists
+    viewport: Rect,
+
+    // Time and cell of the last mouse click, to detect double-clicks
+    last_click: Option<(std::time::Instant, u16, u16)>,
 
     // Status/error messages
     pub flash_message: Option<(String, std::time::Instant)>,
+    pub flash_is_error: bool,
     pub loading: bool,
 
     // IO channel
     pub io_tx: mpsc::UnboundedSender<IoEvent>,
+    // Hooks set off since the main loop last ran them, with their payloads
+    pub fired_hooks: Vec<(Hook, serde_json::Value)>,
+    // Listens to submit since the main loop last sent them on
+    pub scrobbles: Vec<Scrobble>,
+    listens: ListenTracker,
+    plays: PlayTracker,
+
+    // Auth recovery and rate limiting
+    reauthenticating: bool,
+    rate_limited_until: Option<std::time::Instant>,
 
-    // Tick counter for polling
-    tick_count: u32,
+    // Now playing polling
+    next_poll_at: Option<std::time::Instant>,
+    focused: bool,
 
     // Local progress interpolation
     last_playback_update: Option<std::time::Instant>,
+
+    // Optimistic playback state: commands in flight, the state to roll back
+    // to if one fails, and when to re-sync with the server
+    pending_playback_commands: u32,
+    playback_snapshot: Option<(bool, u8)>,
+    reconcile_at: Option<std::time::Instant>,
 }
 
 impl App {
-    pub fn new(io_tx: mpsc::UnboundedSender<IoEvent>) -> Self {
+    pub fn new(io_tx: mpsc::UnboundedSender<IoEvent>, settings: Settings) -> Self {
         Self {
             running: true,
-            screen: Screen::Library,
+            screen: settings.default_screen,
+            settings,
             input_mode: InputMode::Normal,
             active_panel: Panel::Left,
             show_help: false,
+            help_index: 0,
+            palette: None,
             now_playing: None,
             is_playing: false,
             volume: 50,
             playlists: Vec::new(),
             playlist_index: 0,
+            playlist_offset: 0,
             playlist_tracks: Vec::new(),
             track_index: 0,
+            track_offset: 0,
             selected_playlist_id: None,
             search_input: String::new(),
             search_results: Vec::new(),
             search_index: 0,
+            search_offset: 0,
             liked_songs: Vec::new(),
             liked_index: 0,
+            liked_offset: 0,
             liked_track_ids: std::collections::HashSet::new(),
+            liked_songs_revalidated: false,
+            stats: None,
+            stats_period: Period::default(),
+            stats_index: 0,
+            stats_offset: 0,
             devices: Vec::new(),
+            show_devices: false,
+            device_index: 0,
+            profile: crate::config::DEFAULT_PROFILE.to_string(),
+            profiles: Vec::new(),
+            show_profiles: false,
+            login_url: None,
+            profile_index: 0,
+            party: PartyQueue::default(),
+            show_party: false,
+            party_index: 0,
+            pending_keys: Vec::new(),
+            count: None,
+            viewport: Rect::default(),
+            last_click: None,
             flash_message: None,
+            flash_is_error: false,
             loading: false,
             io_tx,
-            tick_count: 0,
+            fired_hooks: Vec::new(),
+            scrobbles: Vec::new(),
+            listens: ListenTracker::default(),
+            plays: PlayTracker::default(),
+            reauthenticating: false,
+            rate_limited_until: None,
+            next_poll_at: None,
+            focused: true,
             last_playback_update: None,
+            pending_playback_commands: 0,
+            playback_snapshot: None,
+            reconcile_at: None,
+        }
+    }
+
+    /// Feeds a key press into the keymap, returning the command once a
+    /// binding is complete.
+    pub fn resolve_key(&mut self, key: Key) -> Option<Command> {
+        // Digits start a count, unless they are bound to something
+        if let Some(digit) = key.digit().filter(|_| self.pending_keys.is_empty()) {
+            let unbound = matches!(self.settings.keymap.lookup(&[key]), KeyMatch::None);
+            if unbound && (digit > 0 || self.count.is_some()) {
+                let count = self.count.unwrap_or(0).saturating_mul(10);
+                self.count = Some(count.saturating_add(digit));
+                return None;
+            }
+        }
+
+        self.pending_keys.push(key);
+        match self.settings.keymap.lookup(&self.pending_keys) {
+            KeyMatch::Command(command) => {
+                self.pending_keys.clear();
+                if !command.is_motion() {
+                    self.count = None;
+                }
+                Some(command)
+            }
+            KeyMatch::Pending => None,
+            KeyMatch::None => {
+                // A broken-off chord: start over from this key
+                let retry = self.pending_keys.len() > 1;
+                self.pending_keys.clear();
+                if retry {
+                    self.resolve_key(key)
+                } else {
+                    self.count = None;
+                    None
+                }
+            }
+        }
+    }
+
+    /// Runs a command in normal mode, from a key binding or the palette.
+    pub fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Quit => {
+                self.running = false;
+            }
+            Command::Help => {
+                self.show_help = true;
+                self.help_index = 0;
+            }
+            Command::Back => {}
+
+            // Screen navigation
+            Command::PrevScreen => {
+                self.prev_screen();
+            }
+            Command::NextScreen => {
+                if self.screen == Screen::Library {
+                    self.toggle_panel();
+                } else {
+                    self.next_screen();
+                }
+            }
+
+            // List navigation
+            Command::MoveDown
+            | Command::MoveUp
+            | Command::HalfPageDown
+            | Command::HalfPageUp
+            | Command::PageDown
+            | Command::PageUp
+            | Command::GoToTop
+            | Command::GoToBottom
+            | Command::ViewTop
+            | Command::ViewMiddle
+            | Command::ViewBottom => {
+                self.move_cursor(command);
+            }
+            Command::JumpToPlaying => {
+                self.jump_to_playing();
+            }
+            Command::Select => {
+                self.on_enter();
+            }
+
+            // Search
+            Command::Search => {
+                self.screen = Screen::Search;
+                self.input_mode = InputMode::Editing;
+                self.search_input.clear();
+            }
+
+            // Playback controls
+            Command::PlayPause => {
+                self.play_pause();
+            }
+            Command::NextTrack => {
+                self.dispatch_playback(IoEvent::NextTrack);
+            }
+            Command::PreviousTrack => {
+                self.dispatch_playback(IoEvent::PreviousTrack);
+            }
+            Command::VolumeUp => {
+                self.volume_up();
+            }
+            Command::VolumeDown => {
+                self.volume_down();
+            }
+
+            // Like toggle
+            Command::ToggleLike => {
+                self.toggle_like();
+            }
+            Command::ToggleLikeNowPlaying => {
+                self.toggle_like_now_playing();
+            }
+
+            // Devices and accounts
+            Command::Devices => {
+                self.open_device_picker();
+            }
+            Command::Accounts => {
+                self.open_profile_picker();
+            }
+            Command::PartyQueue => {
+                self.show_party = true;
+                self.clamp_party_index();
+            }
+            Command::Stats => {
+                self.cycle_stats_period();
+            }
+            // Only means something in the party queue
+            Command::Remove => {}
+            Command::Palette => {
+                self.open_palette();
+            }
         }
     }
 
@@ -136,13 +398,31 @@ impl App {
     }
 
     pub fn on_tick(&mut self) {
-        self.tick_count += 1;
+        let now = std::time::Instant::now();
+
+        // Hold off polling while rate limited
+        let rate_limited = self.rate_limited_until.is_some_and(|until| now < until);
 
-        // Poll now playing every ~5 seconds (20 ticks at 250ms)
-        if self.tick_count % 20 == 0 {
-            self.dispatch_io(IoEvent::FetchNowPlaying);
+        // Re-sync after playback commands settle
+        if let Some(at) = self.reconcile_at {
+            if std::time::Instant::now() >= at {
+                self.reconcile_at = None;
+                self.dispatch_io(IoEvent::FetchNowPlaying);
+            }
         }
 
+        // Poll now playing on the adaptive schedule, but not while unfocused
+        // unless a notification could be missed
+        let poll_due = self.next_poll_at.is_none_or(|at| now >= at);
+        let watching = self.focused || self.settings.notifications || self.settings.scrobble;
+        if watching && !rate_limited && poll_due {
+            self.poll_now_playing();
+        }
+
+        self.feed_party_queue();
+        self.track_listen();
+        self.track_play();
+
         // Clear flash messages after 5 seconds
         if let Some((_, instant)) = &self.flash_message {
             if instant.elapsed() > std::time::Duration::from_secs(5) {
@@ -154,18 +434,55 @@ impl App {
     pub fn update(&mut self, action: Action) {
         match action {
             Action::NowPlayingUpdated(ctx) => {
-                if let Some(ref ctx) = ctx {
+                let mut ctx = ctx.map(|ctx| *ctx);
+                // Don't let a stale poll clobber optimistic state
+                let settled = self.pending_playback_commands == 0 && self.reconcile_at.is_none();
+                let mut kept_progress = false;
+                if let Some(ctx) = ctx.as_ref().filter(|_| settled) {
                     self.is_playing = ctx.is_playing;
                     if let Some(ref device) = ctx.device.volume_percent {
                         self.volume = *device as u8;
                     }
+                } else if let (Some(ctx), Some(old)) = (ctx.as_mut(), self.now_playing.as_ref()) {
+                    ctx.shuffle_state = old.shuffle_state;
+                    ctx.repeat_state = old.repeat_state;
+                    // A seek only applies to the item it was made on
+                    if hooks::item_key(ctx) == hooks::item_key(old) {
+                        ctx.progress = old.progress;
+                        kept_progress = true;
+                    }
                 }
+                // The first poll only says where playback already was
+                let hooks = match self.last_playback_update {
+                    Some(_) => Hook::transitions(self.now_playing.as_ref(), ctx.as_ref()),
+                    None => Vec::new(),
+                };
                 self.now_playing = ctx;
-                self.last_playback_update = Some(std::time::Instant::now());
+                // Kept progress still runs on from when it was set
+                if !kept_progress {
+                    self.last_playback_update = Some(std::time::Instant::now());
+                }
+                self.next_poll_at = Some(std::time::Instant::now() + self.poll_interval());
+                for hook in hooks {
+                    self.fire_hook(hook, None);
+                }
+                self.track_listen();
+                self.track_play();
+            }
+            Action::CachedLibraryLoaded {
+                playlists,
+                liked_songs,
+            } => {
+                // Fresh data may have raced ahead of the cache
+                if self.playlists.is_empty() {
+                    self.set_playlists(playlists);
+                }
+                if self.liked_songs.is_empty() {
+                    self.set_liked_songs(liked_songs);
+                }
             }
             Action::PlaylistsLoaded(playlists) => {
-                self.playlists = playlists;
-                self.playlist_index = 0;
+                self.set_playlists(playlists);
                 self.loading = false;
             }
             Action::PlaylistTracksLoaded(tracks) => {
@@ -179,35 +496,217 @@ impl App {
                 self.loading = false;
             }
             Action::LikedSongsLoaded(songs) => {
-                self.liked_track_ids.clear();
-                for song in &songs {
-                    if let Some(ref id) = song.track.id {
-                        self.liked_track_ids.insert(id.to_string());
-                    }
-                }
-                self.liked_songs = songs;
-                self.liked_index = 0;
+                self.set_liked_songs(songs);
                 self.loading = false;
             }
             Action::LikeToggled { track_id, is_liked } => {
                 if is_liked {
+                    let track = self.find_track(&track_id).cloned();
                     self.liked_track_ids.insert(track_id);
+                    self.fire_hook(Hook::TrackLiked, track.as_ref());
                 } else {
                     self.liked_track_ids.remove(&track_id);
                 }
             }
-            Action::Error(msg) => {
-                self.flash_message = Some((msg, std::time::Instant::now()));
+            Action::Error { context, error } => {
                 self.loading = false;
+                self.on_error(&context, error);
+            }
+            Action::AddedToPlaylist {
+                playlist_id,
+                snapshot_id,
+            } => {
+                let name = self
+                    .playlists
+                    .iter()
+                    .find(|p| p.id.to_string() == playlist_id)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_else(|| "playlist".to_string());
+                self.set_notice(format!("Added to {}", name));
+                // Pick up the new snapshot, and the new track if it's on screen
+                self.dispatch_io(IoEvent::FetchPlaylists);
+                if self.selected_playlist_id.as_ref() == Some(&playlist_id) {
+                    self.dispatch_io(IoEvent::FetchPlaylistTracks {
+                        playlist_id,
+                        snapshot_id,
+                    });
+                }
+            }
+            Action::AddedToQueue(name) => {
+                self.set_notice(format!("Queued {}", name));
             }
             Action::DevicesLoaded(devices) => {
                 self.devices = devices;
+                self.device_index = self
+                    .devices
+                    .iter()
+                    .position(|d| d.is_active)
+                    .unwrap_or(0);
+                let options = self.device_options();
+                if let Some(palette) = self.palette.as_mut() {
+                    if let Some(prompt) = palette
+                        .prompt
+                        .as_mut()
+                        .filter(|p| p.entry == Entry::TransferPlayback)
+                    {
+                        prompt.options = options;
+                        palette.selected = 0;
+                    }
+                }
+            }
+            Action::PlaybackCommandSucceeded => {
+                self.finish_playback_command();
+            }
+            Action::PlaybackCommandFailed { context, error } => {
+                if let Some((is_playing, volume)) = self.playback_snapshot {
+                    self.rebase_progress();
+                    self.is_playing = is_playing;
+                    self.volume = volume;
+                }
+                self.finish_playback_command();
+                self.on_error(&context, error);
+            }
+            Action::PlayRecorded => {
+                if self.screen == Screen::Stats {
+                    self.load_stats();
+                }
+            }
+            Action::StatsLoaded(stats) => {
+                // Drop answers for a period that's no longer shown
+                if stats.period == self.stats_period {
+                    self.stats = Some(*stats);
+                    self.clamp_stats_index();
+                }
+            }
+            Action::LoginStarted(url) => {
+                self.login_url = Some(url);
+            }
+            Action::ProfileSwitched(profile) => {
+                self.login_url = None;
+                self.reset_account_state();
+                self.profile = profile;
+                self.init();
+            }
+            Action::Authenticated => {
+                self.reauthenticating = false;
+                self.login_url = None;
+                self.dispatch_io(IoEvent::FetchNowPlaying);
+            }
+            Action::AuthenticationFailed { context, error } => {
+                // Whatever went wrong, the next expired token tries again
+                self.reauthenticating = false;
+                self.login_url = None;
+                self.loading = false;
+                match error {
+                    // Refreshing again straight away would just loop
+                    AppError::AuthExpired => self.set_flash(error.to_string()),
+                    error => self.on_error(&context, error),
+                }
+            }
+        }
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+        if focused {
+            // Catch up on anything that changed while we weren't looking
+            self.poll_now_playing();
+        }
+    }
+
+    fn poll_now_playing(&mut self) {
+        // Fallback in case the response never arrives; it reschedules on success
+        self.next_poll_at = Some(std::time::Instant::now() + self.settings.poll_interval);
+        self.dispatch_io(IoEvent::FetchNowPlaying);
+    }
+
+    /// Picks the delay before the next poll: sooner when the current track is
+    /// about to end, later when paused or when there's no active device.
+    fn poll_interval(&self) -> std::time::Duration {
+        let interval = self.settings.poll_interval;
+        if self.now_playing.is_none() {
+            return interval * IDLE_POLL_FACTOR;
+        }
+        if !self.is_playing {
+            return interval * PAUSED_POLL_FACTOR;
+        }
+        match self.interpolated_progress_ms() {
+            Some((progress_ms, duration_ms)) if duration_ms > 0 => {
+                let remaining_ms = (duration_ms - progress_ms).max(0) as u64;
+                let remaining = std::time::Duration::from_millis(remaining_ms);
+                interval.min(remaining + TRACK_END_GRACE)
+            }
+            _ => interval,
+        }
+    }
+
+    /// Replaces the playlists, keeping the same playlist selected if it's
+    /// still there (revalidation can replace a cached list at any time).
+    fn set_playlists(&mut self, playlists: Vec<SimplifiedPlaylist>) {
+        let selected = self.playlists.get(self.playlist_index).map(|p| p.id.clone());
+        self.playlist_index = selected
+            .and_then(|id| playlists.iter().position(|p| p.id == id))
+            .unwrap_or(0);
+        self.playlists = playlists;
+    }
+
+    fn set_liked_songs(&mut self, songs: Vec<SavedTrack>) {
+        self.liked_track_ids.clear();
+        for song in &songs {
+            if let Some(ref id) = song.track.id {
+                self.liked_track_ids.insert(id.to_string());
+            }
+        }
+        self.liked_index = self.liked_index.min(songs.len().saturating_sub(1));
+        self.liked_songs = songs;
+    }
+
+    fn on_error(&mut self, context: &str, error: AppError) {
+        match error {
+            AppError::NoActiveDevice => {
+                self.open_device_picker();
+            }
+            AppError::AuthExpired if !self.reauthenticating => {
+                // A refresh is usually enough; a revoked grant escalates to a full login
+                self.reauthenticating = true;
+                self.dispatch_io(IoEvent::RefreshToken);
+                return;
+            }
+            AppError::AuthExpired => return,
+            AppError::TokenRevoked => {
+                self.reauthenticating = true;
+                self.dispatch_io(IoEvent::Reauthenticate);
+            }
+            AppError::Auth(_) => {
+                self.reauthenticating = false;
+            }
+            AppError::RateLimited { retry_after } => {
+                let wait = std::time::Duration::from_secs(retry_after.unwrap_or(5));
+                self.rate_limited_until = Some(std::time::Instant::now() + wait);
             }
+            _ => {}
+        }
+
+        if error.is_classified() {
+            self.set_flash(error.to_string());
+        } else {
+            self.set_flash(format!("{}: {}", context, error));
         }
     }
 
     pub fn set_flash(&mut self, msg: String) {
         self.flash_message = Some((msg, std::time::Instant::now()));
+        self.flash_is_error = true;
+    }
+
+    /// Shows a message that isn't an error.
+    pub fn set_notice(&mut self, msg: String) {
+        self.flash_message = Some((msg, std::time::Instant::now()));
+        self.flash_is_error = false;
     }
 
     // Navigation helpers
@@ -217,6 +716,13 @@ impl App {
         self.on_screen_change();
     }
 
+    pub fn go_to_screen(&mut self, screen: Screen) {
+        if screen != self.screen {
+            self.screen = screen;
+            self.on_screen_change();
+        }
+    }
+
     pub fn prev_screen(&mut self) {
         self.screen = self.screen.prev();
         self.on_screen_change();
@@ -232,71 +738,701 @@ impl App {
                 }
             }
             Screen::LikedSongs => {
-                if self.liked_songs.is_empty() {
-                    self.loading = true;
+                // Cached songs are shown while the first visit revalidates them
+                if !self.liked_songs_revalidated {
+                    self.liked_songs_revalidated = true;
+                    self.loading = self.liked_songs.is_empty();
                     self.dispatch_io(IoEvent::FetchLikedSongs);
                 }
             }
             Screen::Search => {}
+            Screen::Stats => self.load_stats(),
         }
     }
 
-    pub fn move_up(&mut self) {
-        match self.screen {
-            Screen::Library => {
-                if self.active_panel == Panel::Left {
-                    if self.playlist_index > 0 {
-                        self.playlist_index -= 1;
+    fn load_stats(&mut self) {
+        if self.settings.history {
+            self.dispatch_io(IoEvent::LoadStats(self.stats_period));
+        }
+    }
+
+    /// Shows the stats for the next period, or opens the Stats screen.
+    pub fn cycle_stats_period(&mut self) {
+        if self.screen == Screen::Stats {
+            self.stats_period = self.stats_period.next();
+            self.stats_index = 0;
+            self.load_stats();
+        } else {
+            self.go_to_screen(Screen::Stats);
+        }
+    }
+
+    /// Sends a playback command, remembering the pre-command state so it can
+    /// be restored if the command fails.
+    pub fn dispatch_playback(&mut self, event: IoEvent) {
+        if self.playback_snapshot.is_none() {
+            self.playback_snapshot = Some((self.is_playing, self.volume));
+        }
+        self.pending_playback_commands += 1;
+        self.dispatch_io(event);
+    }
+
+    fn finish_playback_command(&mut self) {
+        self.pending_playback_commands = self.pending_playback_commands.saturating_sub(1);
+        if self.pending_playback_commands == 0 {
+            self.playback_snapshot = None;
+            self.reconcile_at = Some(std::time::Instant::now() + RECONCILE_DELAY);
+        }
+    }
+
+    pub fn play_pause(&mut self) {
+        let event = if self.is_playing {
+            IoEvent::Pause
+        } else {
+            IoEvent::Resume
+        };
+        self.dispatch_playback(event);
+        self.rebase_progress();
+        self.is_playing = !self.is_playing;
+    }
+
+    pub fn volume_up(&mut self) {
+        self.set_volume(self.volume.saturating_add(self.settings.volume_step).min(100));
+    }
+
+    pub fn volume_down(&mut self) {
+        self.set_volume(self.volume.saturating_sub(self.settings.volume_step));
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.dispatch_playback(IoEvent::SetVolume(volume));
+        self.volume = volume;
+    }
+
+    fn play(&mut self, event: IoEvent) {
+        self.dispatch_playback(event);
+        self.rebase_progress();
+        self.is_playing = true;
+    }
+
+    pub fn open_profile_picker(&mut self) {
+        self.profile_index = self
+            .profiles
+            .iter()
+            .position(|p| *p == self.profile)
+            .unwrap_or(0);
+        self.show_profiles = true;
+    }
+
+    pub fn switch_to_selected_profile(&mut self) {
+        self.show_profiles = false;
+        if let Some(profile) = self.profiles.get(self.profile_index) {
+            if *profile != self.profile {
+                // The play in progress belongs to this account's history
+                if let Some(play) = self.plays.finish() {
+                    self.dispatch_io(IoEvent::RecordPlay(Box::new(play)));
+                }
+                self.loading = true;
+                self.dispatch_io(IoEvent::SwitchProfile(profile.clone()));
+            }
+        }
+    }
+
+    /// Drops everything that belongs to the current account.
+    fn reset_account_state(&mut self) {
+        self.screen = Screen::Library;
+        self.active_panel = Panel::Left;
+        self.now_playing = None;
+        self.is_playing = false;
+        self.playlists.clear();
+        self.playlist_index = 0;
+        self.playlist_tracks.clear();
+        self.track_index = 0;
+        self.selected_playlist_id = None;
+        self.search_results.clear();
+        self.search_index = 0;
+        self.liked_songs.clear();
+        self.liked_index = 0;
+        self.liked_track_ids.clear();
+        self.liked_songs_revalidated = false;
+        self.stats = None;
+        self.stats_index = 0;
+        self.devices.clear();
+        self.device_index = 0;
+        self.loading = false;
+    }
+
+    /// Queues the top suggestion on Spotify once the track playing is
+    /// about to end, one per track.
+    fn feed_party_queue(&mut self) {
+        if self.party.is_empty() || !self.is_playing || self.pending_playback_commands > 0 {
+            return;
+        }
+        let Some((progress_ms, duration_ms)) = self.interpolated_progress_ms() else {
+            return;
+        };
+        let playing = self.now_playing.as_ref().and_then(hooks::item_key);
+        if duration_ms - progress_ms > self.settings.party_lead.as_millis() as i64
+            || playing.is_none()
+            || playing == self.party.fed_during
+        {
+            return;
+        }
+        self.party.fed_during = playing;
+        if let Some(suggestion) = self.party.remove(0) {
+            self.queue_suggestion(suggestion);
+        }
+    }
+
+    fn queue_suggestion(&mut self, suggestion: Suggestion) {
+        let artists: Vec<&str> = suggestion.track.artists.iter().map(|a| a.name.as_str()).collect();
+        self.dispatch_io(IoEvent::AddToQueue {
+            track_uri: suggestion.uri,
+            name: format!("{} - {}", suggestion.track.name, artists.join(", ")),
+        });
+        self.clamp_party_index();
+    }
+
+    /// Skips the vote and queues the selected suggestion right away.
+    pub fn queue_selected_suggestion(&mut self) {
+        if let Some(suggestion) = self.party.remove(self.party_index) {
+            self.queue_suggestion(suggestion);
+        }
+    }
+
+    pub fn remove_selected_suggestion(&mut self) {
+        if self.party.remove(self.party_index).is_some() {
+            self.clamp_party_index();
+        }
+    }
+
+    fn clamp_party_index(&mut self) {
+        self.party_index = self.party_index.min(self.party.suggestions().len().saturating_sub(1));
+    }
+
+    /// The Stats screen's top tracks, the list it selects from.
+    pub fn top_tracks(&self) -> &[Ranked] {
+        self.stats.as_ref().map_or(&[], |stats| &stats.tracks)
+    }
+
+    fn clamp_stats_index(&mut self) {
+        self.stats_index = self.stats_index.min(self.top_tracks().len().saturating_sub(1));
+    }
+
+    pub fn open_device_picker(&mut self) {
+        self.show_devices = true;
+        self.dispatch_io(IoEvent::FetchDevices);
+    }
+
+    pub fn transfer_to_selected_device(&mut self) {
+        if let Some(id) = self
+            .devices
+            .get(self.device_index)
+            .and_then(|d| d.id.clone())
+        {
+            self.dispatch_playback(IoEvent::TransferPlayback(id));
+            self.show_devices = false;
+        }
+    }
+
+    pub fn toggle_shuffle(&mut self) {
+        if let Some(ref ctx) = self.now_playing {
+            self.set_shuffle(!ctx.shuffle_state);
+        }
+    }
+
+    fn set_shuffle(&mut self, state: bool) {
+        let Some(ref mut ctx) = self.now_playing else {
+            return;
+        };
+        ctx.shuffle_state = state;
+        self.dispatch_playback(IoEvent::SetShuffle(state));
+    }
+
+    /// Steps through repeat off, repeat playlist and repeat track.
+    pub fn cycle_repeat(&mut self) {
+        let Some(ref ctx) = self.now_playing else {
+            return;
+        };
+        let state = match ctx.repeat_state {
+            RepeatState::Off => RepeatState::Context,
+            RepeatState::Context => RepeatState::Track,
+            RepeatState::Track => RepeatState::Off,
+        };
+        self.set_repeat(state);
+    }
+
+    fn set_repeat(&mut self, state: RepeatState) {
+        let Some(ref mut ctx) = self.now_playing else {
+            return;
+        };
+        ctx.repeat_state = state;
+        self.dispatch_playback(IoEvent::SetRepeat(state));
+    }
+
+    /// Handles a playback request from outside the TUI.
+    pub fn on_remote(&mut self, remote: Remote) {
+        match remote {
+            Remote::PlayPause => self.play_pause(),
+            Remote::Play if !self.is_playing => self.play_pause(),
+            Remote::Pause if self.is_playing => self.play_pause(),
+            Remote::Play | Remote::Pause => {}
+            Remote::Next => self.dispatch_playback(IoEvent::NextTrack),
+            Remote::Previous => self.dispatch_playback(IoEvent::PreviousTrack),
+            Remote::Seek(offset_ms) => {
+                if let Some((progress_ms, duration_ms)) = self.interpolated_progress_ms() {
+                    self.seek_to((progress_ms + offset_ms).clamp(0, duration_ms));
+                }
+            }
+            Remote::SetPosition(position_ms) => self.seek_to(position_ms),
+            Remote::SetVolume(volume) => self.set_volume(volume.min(100)),
+            Remote::ChangeVolume(by) => {
+                self.set_volume((self.volume as i32 + by).clamp(0, 100) as u8);
+            }
+            Remote::SetShuffle(state) => self.set_shuffle(state),
+            Remote::SetRepeat(state) => self.set_repeat(state),
+            Remote::ToggleLiked => self.toggle_like_now_playing(),
+            Remote::Suggest { guest, track } => {
+                if self.party.suggest(guest, *track) {
+                    self.clamp_party_index();
+                }
+            }
+            Remote::Vote { guest, id } => self.party.vote(guest, id),
+            Remote::SetLiked(liked) => {
+                let Some(id) = self.now_playing_track_id() else {
+                    return;
+                };
+                if self.liked_track_ids.contains(&id) != liked {
+                    self.request_toggle_like(id);
+                }
+            }
+        }
+    }
+
+    pub fn open_palette(&mut self) {
+        self.palette = Some(Palette::default());
+    }
+
+    /// What the palette currently offers: entry labels with their key
+    /// bindings, or the options of the current prompt.
+    pub fn palette_items(&self) -> Vec<(String, String)> {
+        self.palette
+            .as_ref()
+            .map(|palette| self.items_for(palette))
+            .unwrap_or_default()
+    }
+
+    /// Indices into `palette_items` that match the input, best first.
+    pub fn palette_matches(&self) -> Vec<usize> {
+        self.palette
+            .as_ref()
+            .map(|palette| self.matches_for(palette))
+            .unwrap_or_default()
+    }
+
+    fn items_for(&self, palette: &Palette) -> Vec<(String, String)> {
+        match palette.prompt {
+            Some(ref prompt) => prompt
+                .options
+                .iter()
+                .map(|(label, _)| (label.clone(), String::new()))
+                .collect(),
+            None => palette::ENTRIES
+                .iter()
+                .map(|entry| {
+                    let keys = match entry {
+                        Entry::Run(command) => self.settings.keymap.keys_for(*command).join(" / "),
+                        _ => String::new(),
+                    };
+                    (entry.label(), keys)
+                })
+                .collect(),
+        }
+    }
+
+    fn matches_for(&self, palette: &Palette) -> Vec<usize> {
+        let labels: Vec<String> = self.items_for(palette).into_iter().map(|(l, _)| l).collect();
+        palette::filter(&palette.input, &labels)
+    }
+
+    pub fn palette_move(&mut self, down: bool) {
+        let count = self.palette_matches().len();
+        if let Some(ref mut palette) = self.palette {
+            palette.selected = if down {
+                (palette.selected + 1).min(count.saturating_sub(1))
+            } else {
+                palette.selected.saturating_sub(1)
+            };
+        }
+    }
+
+    pub fn palette_input(&mut self, c: char) {
+        if let Some(ref mut palette) = self.palette {
+            palette.input.push(c);
+            palette.selected = 0;
+        }
+    }
+
+    /// Deletes a character; on an empty prompt, goes back to the entries.
+    pub fn palette_backspace(&mut self) {
+        if let Some(ref mut palette) = self.palette {
+            if palette.input.pop().is_none() {
+                palette.prompt = None;
+            }
+            palette.selected = 0;
+        }
+    }
+
+    pub fn palette_submit(&mut self) {
+        let Some(palette) = self.palette.take() else {
+            return;
+        };
+        let matches = self.matches_for(&palette);
+        match palette.prompt {
+            Some(prompt) if prompt.free_text => {
+                self.run_palette_prompt(prompt.entry, palette.input.trim().to_string());
+            }
+            Some(prompt) => {
+                let chosen = matches.get(palette.selected).and_then(|i| prompt.options.get(*i));
+                match chosen {
+                    Some((_, value)) => self.run_palette_prompt(prompt.entry, value.clone()),
+                    // The devices may not have come back yet
+                    None if prompt.entry == Entry::TransferPlayback
+                        && prompt.options.is_empty() =>
+                    {
+                        self.set_flash("No devices found".to_string())
                     }
-                } else if self.track_index > 0 {
-                    self.track_index -= 1;
+                    None => {}
                 }
             }
-            Screen::Search => {
-                if self.search_index > 0 {
-                    self.search_index -= 1;
+            None => {
+                let chosen = matches.get(palette.selected).and_then(|i| palette::ENTRIES.get(*i));
+                if let Some(entry) = chosen {
+                    self.run_palette_entry(*entry);
                 }
             }
-            Screen::LikedSongs => {
-                if self.liked_index > 0 {
-                    self.liked_index -= 1;
+        }
+    }
+
+    fn run_palette_entry(&mut self, entry: Entry) {
+        let prompt = |title, options, free_text| {
+            Some(Palette {
+                prompt: Some(Prompt {
+                    entry,
+                    title,
+                    options,
+                    free_text,
+                }),
+                ..Palette::default()
+            })
+        };
+        match entry {
+            Entry::Run(command) => self.run_command(command),
+            Entry::GoTo(screen) => self.go_to_screen(screen),
+            Entry::Play => {
+                if !self.is_playing {
+                    self.play_pause();
+                }
+            }
+            Entry::Pause => {
+                if self.is_playing {
+                    self.play_pause();
+                }
+            }
+            Entry::ToggleShuffle => self.toggle_shuffle(),
+            Entry::CycleRepeat => self.cycle_repeat(),
+            Entry::SetVolume => self.palette = prompt(" Volume (0-100) ", Vec::new(), true),
+            Entry::Seek => self.palette = prompt(" Seek to (m:ss) ", Vec::new(), true),
+            Entry::SearchFor => self.palette = prompt(" Search for ", Vec::new(), true),
+            Entry::TransferPlayback => {
+                // The list is refreshed when the devices come back
+                self.dispatch_io(IoEvent::FetchDevices);
+                self.palette = prompt(" Transfer playback to ", self.device_options(), false);
+            }
+            Entry::AddToPlaylist => {
+                if self.selected_track_id().is_none() {
+                    self.set_flash("No track selected".to_string());
+                    return;
+                }
+                if self.playlists.is_empty() {
+                    self.set_flash("No playlists".to_string());
+                    return;
                 }
+                let options = self
+                    .playlists
+                    .iter()
+                    .map(|p| (p.name.clone(), p.id.to_string()))
+                    .collect();
+                self.palette = prompt(" Add to playlist ", options, false);
+            }
+            Entry::SwitchAccount => {
+                let options = self.profiles.iter().map(|p| (p.clone(), p.clone())).collect();
+                self.palette = prompt(" Switch account to ", options, false);
             }
         }
     }
 
+    /// Devices to transfer playback to, as palette options.
+    fn device_options(&self) -> Vec<(String, String)> {
+        self.devices
+            .iter()
+            .filter_map(|d| Some((d.name.clone(), d.id.clone()?)))
+            .collect()
+    }
+
+    fn run_palette_prompt(&mut self, entry: Entry, value: String) {
+        match entry {
+            Entry::SetVolume => match value.parse::<u8>() {
+                Ok(volume) if volume <= 100 => self.set_volume(volume),
+                _ => self.set_flash(format!("Invalid volume: {}", value)),
+            },
+            Entry::Seek => match parse_position(&value) {
+                Some(position_ms) => self.seek_to(position_ms),
+                None => self.set_flash(format!("Invalid position: {}", value)),
+            },
+            Entry::SearchFor if !value.is_empty() => {
+                self.go_to_screen(Screen::Search);
+                self.search_input = value.clone();
+                self.loading = true;
+                self.dispatch_io(IoEvent::Search(value));
+            }
+            Entry::TransferPlayback => {
+                self.dispatch_playback(IoEvent::TransferPlayback(value));
+            }
+            Entry::AddToPlaylist => {
+                if let Some(track_id) = self.selected_track_id() {
+                    self.dispatch_io(IoEvent::AddToPlaylist {
+                        playlist_id: value,
+                        track_id,
+                    });
+                }
+            }
+            Entry::SwitchAccount => {
+                if let Some(index) = self.profiles.iter().position(|p| *p == value) {
+                    self.profile_index = index;
+                    self.switch_to_selected_profile();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        self.apply_motion(Command::MoveUp, None);
+    }
+
     pub fn move_down(&mut self) {
+        self.apply_motion(Command::MoveDown, None);
+    }
+
+    /// Applies a motion command to the open popup's list or the current
+    /// screen's, with any count typed before it.
+    pub fn move_cursor(&mut self, command: Command) {
+        let count = self.count.take();
+        self.apply_motion(command, count);
+    }
+
+    fn apply_motion(&mut self, command: Command, count: Option<usize>) {
+        let list = self.current_list();
+        let view = self.list_view(list).apply(command, count);
+        self.set_list_view(list, view);
+    }
+
+    /// Selects the playing track in the current screen's list, or in the
+    /// library's playlist panel, the playlist it's playing from.
+    pub fn jump_to_playing(&mut self) {
+        let Some(id) = self.now_playing_track_id() else {
+            self.set_notice("Nothing is playing".to_string());
+            return;
+        };
+        let same_track =
+            |track: &FullTrack| track.id.as_ref().is_some_and(|t| t.to_string() == id);
+        let index = match self.current_list() {
+            List::Playlists => {
+                let context = self.now_playing.as_ref().and_then(|ctx| ctx.context.as_ref());
+                context.and_then(|context| {
+                    self.playlists.iter().position(|p| p.id.to_string() == context.uri)
+                })
+            }
+            List::Tracks => self.playlist_tracks.iter().position(same_track),
+            List::Search => self.search_results.iter().position(same_track),
+            List::Liked => self.liked_songs.iter().position(|saved| same_track(&saved.track)),
+            List::Stats => self.top_tracks().iter().position(|ranked| ranked.key == id),
+            List::Devices | List::Profiles | List::Party | List::Help => return,
+        };
+        let Some(index) = index else {
+            self.set_notice("The playing track isn't in this list".to_string());
+            return;
+        };
+
+        // Scroll it to the middle of the view
+        let list = self.current_list();
+        let mut view = self.list_view(list);
+        view.selected = index;
+        view.offset = index.saturating_sub(view.height / 2);
+        self.set_list_view(list, view.scrolled_into_view());
+    }
+
+    /// Remembers the terminal area the UI is about to be drawn into, and
+    /// scrolls every list to keep its selected row on screen.
+    pub fn set_viewport(&mut self, area: Rect) {
+        self.viewport = area;
+        for list in [List::Playlists, List::Tracks, List::Search, List::Liked, List::Stats] {
+            let view = self.list_view(list).scrolled_into_view();
+            self.set_list_view(list, view);
+        }
+    }
+
+    /// The list motions apply to.
+    fn current_list(&self) -> List {
+        if self.show_help {
+            return List::Help;
+        }
+        if self.show_profiles {
+            return List::Profiles;
+        }
+        if self.show_party {
+            return List::Party;
+        }
+        if self.show_devices {
+            return List::Devices;
+        }
+        match self.screen {
+            Screen::Library if self.active_panel == Panel::Left => List::Playlists,
+            Screen::Library => List::Tracks,
+            Screen::Search => List::Search,
+            Screen::LikedSongs => List::Liked,
+            Screen::Stats => List::Stats,
+        }
+    }
+
+    fn list_view(&self, list: List) -> ListView {
+        let rows = |screen, panel| hit_test::list_rows(screen, panel, self.viewport).height;
+        let popup_rows = |area: Rect| area.height.saturating_sub(2);
+        let (selected, offset, len, height) = match list {
+            List::Playlists => (
+                self.playlist_index,
+                self.playlist_offset,
+                self.playlists.len(),
+                rows(Screen::Library, Panel::Left),
+            ),
+            List::Tracks => (
+                self.track_index,
+                self.track_offset,
+                self.playlist_tracks.len(),
+                rows(Screen::Library, Panel::Right),
+            ),
+            List::Search => (
+                self.search_index,
+                self.search_offset,
+                self.search_results.len(),
+                rows(Screen::Search, Panel::Left),
+            ),
+            List::Liked => (
+                self.liked_index,
+                self.liked_offset,
+                self.liked_songs.len(),
+                rows(Screen::LikedSongs, Panel::Left),
+            ),
+            List::Stats => (
+                self.stats_index,
+                self.stats_offset,
+                self.top_tracks().len(),
+                rows(Screen::Stats, Panel::Left),
+            ),
+            List::Devices => {
+                let height = popup_rows(devices::popup_area(self, self.viewport));
+                let offset = self.device_index.saturating_sub(height.saturating_sub(1) as usize);
+                (self.device_index, offset, self.devices.len(), height)
+            }
+            List::Profiles => {
+                let height = popup_rows(profiles::popup_area(self, self.viewport));
+                let offset = self.profile_index.saturating_sub(height.saturating_sub(1) as usize);
+                (self.profile_index, offset, self.profiles.len(), height)
+            }
+            List::Party => {
+                let height = popup_rows(party::popup_area(self, self.viewport));
+                let offset = self.party_index.saturating_sub(height.saturating_sub(1) as usize);
+                (self.party_index, offset, self.party.suggestions().len(), height)
+            }
+            List::Help => {
+                let height = popup_rows(help::popup_area(self, self.viewport));
+                let offset = self.help_index.saturating_sub(height.saturating_sub(1) as usize);
+                let len = self.settings.keymap.help_entries().len();
+                (self.help_index, offset, len, height)
+            }
+        };
+        ListView {
+            selected,
+            offset,
+            len,
+            height: (height as usize).max(1),
+        }
+    }
+
+    fn set_list_view(&mut self, list: List, view: ListView) {
+        let (index, offset) = match list {
+            List::Playlists => (&mut self.playlist_index, &mut self.playlist_offset),
+            List::Tracks => (&mut self.track_index, &mut self.track_offset),
+            List::Search => (&mut self.search_index, &mut self.search_offset),
+            List::Liked => (&mut self.liked_index, &mut self.liked_offset),
+            List::Stats => (&mut self.stats_index, &mut self.stats_offset),
+            // Popups work out their scroll position as they are drawn
+            List::Devices => {
+                self.device_index = view.selected;
+                return;
+            }
+            List::Profiles => {
+                self.profile_index = view.selected;
+                return;
+            }
+            List::Party => {
+                self.party_index = view.selected;
+                return;
+            }
+            List::Help => {
+                self.help_index = view.selected;
+                return;
+            }
+        };
+        *index = view.selected;
+        *offset = view.offset;
+    }
+
+    /// Selects a row in the current screen's list (the active panel's, in
+    /// the library).
+    pub fn select_row(&mut self, index: usize) {
         match self.screen {
             Screen::Library => {
                 if self.active_panel == Panel::Left {
-                    if !self.playlists.is_empty()
-                        && self.playlist_index < self.playlists.len() - 1
-                    {
-                        self.playlist_index += 1;
-                    }
-                } else if !self.playlist_tracks.is_empty()
-                    && self.track_index < self.playlist_tracks.len() - 1
-                {
-                    self.track_index += 1;
+                    self.playlist_index = index.min(self.playlists.len().saturating_sub(1));
+                } else {
+                    self.track_index = index.min(self.playlist_tracks.len().saturating_sub(1));
                 }
             }
             Screen::Search => {
-                if !self.search_results.is_empty()
-                    && self.search_index < self.search_results.len() - 1
-                {
-                    self.search_index += 1;
-                }
+                self.search_index = index.min(self.search_results.len().saturating_sub(1));
             }
             Screen::LikedSongs => {
-                if !self.liked_songs.is_empty()
-                    && self.liked_index < self.liked_songs.len() - 1
-                {
-                    self.liked_index += 1;
-                }
+                self.liked_index = index.min(self.liked_songs.len().saturating_sub(1));
+            }
+            Screen::Stats => {
+                self.stats_index = index.min(self.top_tracks().len().saturating_sub(1));
             }
         }
     }
 
+    /// Records a click, returning whether it completes a double-click.
+    pub fn register_click(&mut self, column: u16, row: u16) -> bool {
+        let now = std::time::Instant::now();
+        let double = self.last_click.is_some_and(|(at, c, r)| {
+            (c, r) == (column, row) && now.duration_since(at) < DOUBLE_CLICK_INTERVAL
+        });
+        // A third click starts a new pair
+        self.last_click = if double { None } else { Some((now, column, row)) };
+        double
+    }
+
     pub fn toggle_panel(&mut self) {
         self.active_panel = match self.active_panel {
             Panel::Left => Panel::Right,
@@ -311,15 +1447,19 @@ impl App {
                     // Select playlist, fetch tracks
                     if let Some(playlist) = self.playlists.get(self.playlist_index) {
                         let id = playlist.id.to_string();
+                        let snapshot_id = playlist.snapshot_id.clone();
                         self.selected_playlist_id = Some(id.clone());
                         self.loading = true;
-                        self.dispatch_io(IoEvent::FetchPlaylistTracks(id));
+                        self.dispatch_io(IoEvent::FetchPlaylistTracks {
+                            playlist_id: id,
+                            snapshot_id,
+                        });
                         self.active_panel = Panel::Right;
                     }
                 } else {
                     // Play selected track in playlist context
                     if let Some(ref playlist_id) = self.selected_playlist_id {
-                        self.dispatch_io(IoEvent::PlayTrackInContext {
+                        self.play(IoEvent::PlayTrackInContext {
                             context_uri: playlist_id.clone(),
                             offset: self.track_index,
                         });
@@ -339,7 +1479,7 @@ impl App {
                     // Play selected search result
                     if let Some(track) = self.search_results.get(self.search_index) {
                         if let Some(ref id) = track.id {
-                            self.dispatch_io(IoEvent::PlayTrack(id.to_string()));
+                            self.play(IoEvent::PlayTrack(id.to_string()));
                         }
                     }
                 }
@@ -347,10 +1487,15 @@ impl App {
             Screen::LikedSongs => {
                 if let Some(saved_track) = self.liked_songs.get(self.liked_index) {
                     if let Some(ref id) = saved_track.track.id {
-                        self.dispatch_io(IoEvent::PlayTrack(id.to_string()));
+                        self.play(IoEvent::PlayTrack(id.to_string()));
                     }
                 }
             }
+            Screen::Stats => {
+                if let Some(uri) = self.selected_top_track() {
+                    self.play(IoEvent::PlayTrack(uri.to_string()));
+                }
+            }
         }
     }
 
@@ -364,7 +1509,30 @@ impl App {
     }
 
     pub fn toggle_like(&mut self) {
-        let track_id = match self.screen {
+        if let Some(id) = self.selected_track_id() {
+            self.request_toggle_like(id);
+        }
+    }
+
+    /// Likes or unlikes `track_id`, passing the track along when the app has
+    /// it so the library cache can add it without fetching.
+    fn request_toggle_like(&mut self, track_id: String) {
+        let currently_liked = self.liked_track_ids.contains(&track_id);
+        let track = if currently_liked {
+            None
+        } else {
+            self.find_track(&track_id).cloned().map(Box::new)
+        };
+        self.dispatch_io(IoEvent::ToggleLike {
+            track_id,
+            track,
+            currently_liked,
+        });
+    }
+
+    /// The track selected on the current screen, or else the one playing.
+    fn selected_track_id(&self) -> Option<String> {
+        match self.screen {
             Screen::Library => {
                 self.playlist_tracks
                     .get(self.track_index)
@@ -383,28 +1551,98 @@ impl App {
                     .and_then(|t| t.track.id.as_ref())
                     .map(|id| id.to_string())
             }
+            Screen::Stats => self.selected_top_track().map(str::to_string),
         }
-        .or_else(|| self.now_playing_track_id());
+        .or_else(|| self.now_playing_track_id())
+    }
 
-        if let Some(id) = track_id {
-            let currently_liked = self.liked_track_ids.contains(&id);
-            self.dispatch_io(IoEvent::ToggleLike {
-                track_id: id,
-                currently_liked,
-            });
-        }
+    /// The URI of the top track selected on the Stats screen, unless it's
+    /// a local file.
+    fn selected_top_track(&self) -> Option<&str> {
+        let ranked = self.top_tracks().get(self.stats_index)?;
+        Some(ranked.key.as_str()).filter(|key| key.starts_with("spotify:track:"))
     }
 
     pub fn toggle_like_now_playing(&mut self) {
         if let Some(id) = self.now_playing_track_id() {
-            let currently_liked = self.liked_track_ids.contains(&id);
-            self.dispatch_io(IoEvent::ToggleLike {
-                track_id: id,
-                currently_liked,
-            });
+            self.request_toggle_like(id);
         }
     }
 
+    /// The status as the CLI's `status --json` prints it, but with the app's
+    /// own idea of the play state, progress and volume, which run ahead of
+    /// the last poll.
+    pub fn status_json(&self) -> serde_json::Value {
+        let mut status = cli::status_json(self.now_playing.as_ref());
+        if self.now_playing.is_some() {
+            status["is_playing"] = self.is_playing.into();
+            if let Some((progress_ms, _)) = self.interpolated_progress_ms() {
+                status["progress_ms"] = progress_ms.into();
+            }
+            status["device"]["volume_percent"] = self.volume.into();
+            if let Some(id) = self.now_playing_track_id() {
+                status["liked"] = self.liked_track_ids.contains(&id).into();
+            }
+        }
+        status
+    }
+
+    /// Queues `hook` for the main loop, with the status as its payload and
+    /// `track` in place of the item if it's about another track.
+    fn fire_hook(&mut self, hook: Hook, track: Option<&FullTrack>) {
+        let mut payload = self.status_json();
+        if let Some(track) = track {
+            payload["item"] = cli::track_json(track);
+            if let Some(id) = &track.id {
+                payload["liked"] = self.liked_track_ids.contains(&id.to_string()).into();
+            }
+        }
+        payload["event"] = hook.name().into();
+        self.fired_hooks.push((hook, payload));
+    }
+
+    /// Follows how long the current track has played, for scrobbling.
+    fn track_listen(&mut self) {
+        if !self.settings.scrobble {
+            return;
+        }
+        let progress_ms = self.interpolated_progress_ms().map_or(0, |(progress_ms, _)| progress_ms);
+        let scrobble = self.listens.update(self.now_playing.as_ref(), self.is_playing, progress_ms);
+        self.scrobbles.extend(scrobble);
+    }
+
+    /// Follows the current track for the listening history, recording the
+    /// last one once it's over.
+    fn track_play(&mut self) {
+        if !self.settings.history {
+            return;
+        }
+        let progress_ms = self.interpolated_progress_ms().map_or(0, |(progress_ms, _)| progress_ms);
+        let finished = self.plays.update(self.now_playing.as_ref(), self.is_playing, progress_ms);
+        if let Some(play) = finished {
+            self.dispatch_io(IoEvent::RecordPlay(Box::new(play)));
+        }
+    }
+
+    /// Ends the play in progress, for recording before exit.
+    pub fn finish_play(&mut self) -> Option<Play> {
+        self.plays.finish()
+    }
+
+    /// A track the app has loaded, wherever it's shown.
+    fn find_track(&self, track_id: &str) -> Option<&FullTrack> {
+        let now_playing = self.now_playing.as_ref().and_then(|ctx| match &ctx.item {
+            Some(PlayableItem::Track(track)) => Some(track),
+            _ => None,
+        });
+        now_playing
+            .into_iter()
+            .chain(&self.playlist_tracks)
+            .chain(&self.search_results)
+            .chain(self.liked_songs.iter().map(|saved| &saved.track))
+            .find(|track| track.id.as_ref().is_some_and(|id| id.to_string() == track_id))
+    }
+
     pub fn current_track_name(&self) -> Option<String> {
         self.now_playing.as_ref().and_then(|ctx| {
             ctx.item.as_ref().map(|item| match item {
@@ -418,7 +1656,20 @@ impl App {
         })
     }
 
-    fn interpolated_progress_ms(&self) -> Option<(i64, i64)> {
+    /// Folds the interpolated progress into the stored context so that
+    /// changing `is_playing` doesn't make the progress jump.
+    fn rebase_progress(&mut self) {
+        if let Some((progress_ms, _)) = self.interpolated_progress_ms() {
+            if let Some(ref mut ctx) = self.now_playing {
+                ctx.progress = Some(chrono::Duration::milliseconds(progress_ms));
+            }
+            self.last_playback_update = Some(std::time::Instant::now());
+        }
+    }
+
+    /// Position and length of the current item in milliseconds, with the
+    /// position advanced locally since the last poll.
+    pub fn interpolated_progress_ms(&self) -> Option<(i64, i64)> {
         let ctx = self.now_playing.as_ref()?;
         let base_ms = ctx.progress.map(|d| d.num_milliseconds()).unwrap_or(0);
         let duration_ms = ctx.item.as_ref().map(|item| match item {
@@ -439,29 +1690,31 @@ impl App {
         Some((progress, duration_ms))
     }
 
+    /// Seeks to a point in the current track, given as a fraction of its
+    /// length.
+    pub fn seek(&mut self, fraction: f64) {
+        if let Some((_, duration_ms)) = self.interpolated_progress_ms() {
+            self.seek_to((duration_ms as f64 * fraction.clamp(0.0, 1.0)) as i64);
+        }
+    }
+
+    fn seek_to(&mut self, position_ms: i64) {
+        let Some(ref mut ctx) = self.now_playing else {
+            return;
+        };
+        ctx.progress = Some(chrono::Duration::milliseconds(position_ms));
+        self.last_playback_update = Some(std::time::Instant::now());
+        self.dispatch_playback(IoEvent::Seek(position_ms));
+    }
+
+    /// How far into the current track playback is, from 0.0 to 1.0.
     pub fn progress_fraction(&self) -> f64 {
-        self.now_playing
-            .as_ref()
-            .map(|ctx| {
-                let progress = ctx
-                    .progress
-                    .map(|d| d.num_milliseconds() as f64)
-                    .unwrap_or(0.0);
-                let duration = ctx
-                    .item
-                    .as_ref()
-                    .map(|item| match item {
-                        PlayableItem::Track(t) => t.duration.num_milliseconds() as f64,
-                        PlayableItem::Episode(e) => e.duration.num_milliseconds() as f64,
-                    })
-                    .unwrap_or(1.0);
-                if duration > 0.0 {
-                    progress / duration
-                } else {
-                    0.0
-                }
-            })
-            .unwrap_or(0.0)
+        match self.interpolated_progress_ms() {
+            Some((progress_ms, duration_ms)) if duration_ms > 0 => {
+                progress_ms as f64 / duration_ms as f64
+            }
+            _ => 0.0,
+        }
     }
 
     pub fn progress_text(&self) -> String {
@@ -477,15 +1730,179 @@ impl App {
     }
 
     pub fn init(&mut self) {
-        self.dispatch_io(IoEvent::FetchNowPlaying);
+        self.screen = self.settings.default_screen;
+        self.dispatch_io(IoEvent::LoadCachedLibrary);
+        self.poll_now_playing();
         self.dispatch_io(IoEvent::FetchPlaylists);
         self.dispatch_io(IoEvent::FetchDevices);
+        // Playlists are always fetched above; other screens load on first visit
+        if self.screen != Screen::Library {
+            self.on_screen_change();
+        }
     }
 }
 
-fn format_duration(ms: i64) -> String {
+/// The lists motions move through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum List {
+    Playlists,
+    Tracks,
+    Search,
+    Liked,
+    Stats,
+    Devices,
+    Profiles,
+    Party,
+    Help,
+}
+
+/// A list's selected row and scroll position, and how many rows fit.
+#[derive(Debug, Clone, Copy)]
+struct ListView {
+    selected: usize,
+    /// The first row on screen.
+    offset: usize,
+    len: usize,
+    height: usize,
+}
+
+impl ListView {
+    /// Applies a motion `count` times, or for `gg` and `G`, goes to row
+    /// `count`.
+    fn apply(mut self, command: Command, count: Option<usize>) -> Self {
+        if self.len == 0 {
+            return self;
+        }
+        let n = count.unwrap_or(1);
+        let last = self.len - 1;
+        let visible_last = (self.offset + self.height).min(self.len) - 1;
+        match command {
+            Command::MoveDown => self.selected = self.selected.saturating_add(n).min(last),
+            Command::MoveUp => self.selected = self.selected.saturating_sub(n),
+            Command::HalfPageDown => self.scroll_down((self.height / 2).max(1).saturating_mul(n)),
+            Command::HalfPageUp => self.scroll_up((self.height / 2).max(1).saturating_mul(n)),
+            Command::PageDown => self.scroll_down(self.height.saturating_mul(n)),
+            Command::PageUp => self.scroll_up(self.height.saturating_mul(n)),
+            Command::GoToTop => self.selected = count.map_or(0, |n| n - 1).min(last),
+            Command::GoToBottom => self.selected = count.map_or(last, |n| n - 1).min(last),
+            Command::ViewTop => {
+                self.selected = self.offset.saturating_add(n - 1).min(visible_last);
+            }
+            Command::ViewMiddle => self.selected = (self.offset + visible_last) / 2,
+            Command::ViewBottom => {
+                self.selected = visible_last.saturating_sub(n - 1).max(self.offset);
+            }
+            _ => {}
+        }
+        self.scrolled_into_view()
+    }
+
+    /// Moves the view and the selection together, like `Ctrl-d` in vim.
+    fn scroll_down(&mut self, rows: usize) {
+        let max_offset = self.len.saturating_sub(self.height);
+        self.offset = self.offset.saturating_add(rows).min(max_offset);
+        self.selected = self.selected.saturating_add(rows).min(self.len - 1);
+    }
+
+    fn scroll_up(&mut self, rows: usize) {
+        self.offset = self.offset.saturating_sub(rows);
+        self.selected = self.selected.saturating_sub(rows);
+    }
+
+    /// Scrolls just far enough to show the selected row, without leaving
+    /// empty space below the last one.
+    fn scrolled_into_view(mut self) -> Self {
+        let max_offset = self.len.saturating_sub(self.height);
+        self.offset = self.offset.min(self.selected).min(max_offset);
+        if self.selected >= self.offset + self.height {
+            self.offset = self.selected + 1 - self.height;
+        }
+        self
+    }
+}
+
+/// Parses `m:ss` or plain seconds into milliseconds.
+fn parse_position(text: &str) -> Option<i64> {
+    let secs = match text.split_once(':') {
+        Some((mins, secs)) => mins.parse::<i64>().ok()? * 60 + secs.parse::<i64>().ok()?,
+        None => text.parse::<i64>().ok()?,
+    };
+    (secs >= 0).then_some(secs * 1000)
+}
+
+pub fn format_duration(ms: i64) -> String {
     let total_secs = ms / 1000;
     let mins = total_secs / 60;
     let secs = total_secs % 60;
     format!("{}:{:02}", mins, secs)
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(len: usize, height: usize) -> ListView {
+        ListView {
+            selected: 0,
+            offset: 0,
+            len,
+            height,
+        }
+    }
+
+    #[test]
+    fn motions_on_an_empty_list_do_nothing() {
+        let motions = [
+            Command::MoveDown,
+            Command::PageDown,
+            Command::GoToBottom,
+            Command::ViewMiddle,
+        ];
+        for command in motions {
+            let moved = view(0, 10).apply(command, Some(3));
+            assert_eq!((moved.selected, moved.offset), (0, 0));
+        }
+    }
+
+    #[test]
+    fn counts_stop_at_the_ends() {
+        let down = view(10, 4).apply(Command::MoveDown, Some(100));
+        assert_eq!((down.selected, down.offset), (9, 6));
+        let up = down.apply(Command::MoveUp, Some(usize::MAX));
+        assert_eq!((up.selected, up.offset), (0, 0));
+
+        let paged = view(10, 4).apply(Command::PageDown, Some(usize::MAX));
+        assert_eq!((paged.selected, paged.offset), (9, 6));
+        let half = view(10, 4).apply(Command::HalfPageDown, Some(2));
+        assert_eq!((half.selected, half.offset), (4, 4));
+    }
+
+    #[test]
+    fn go_to_row_stops_at_the_last_one() {
+        let past = view(5, 3).apply(Command::GoToBottom, Some(12));
+        assert_eq!((past.selected, past.offset), (4, 2));
+        let top = past.apply(Command::GoToTop, Some(12));
+        assert_eq!(top.selected, 4);
+        assert_eq!(past.apply(Command::GoToTop, None).selected, 0);
+        assert_eq!(view(5, 3).apply(Command::GoToTop, Some(2)).selected, 1);
+        assert_eq!(view(5, 3).apply(Command::GoToBottom, None).selected, 4);
+    }
+
+    #[test]
+    fn view_motions_stay_on_screen() {
+        let scrolled = ListView {
+            selected: 5,
+            offset: 4,
+            len: 20,
+            height: 6,
+        };
+        assert_eq!(scrolled.apply(Command::ViewTop, None).selected, 4);
+        assert_eq!(scrolled.apply(Command::ViewTop, Some(30)).selected, 9);
+        assert_eq!(scrolled.apply(Command::ViewMiddle, None).selected, 6);
+        assert_eq!(scrolled.apply(Command::ViewBottom, Some(30)).selected, 4);
+
+        // A list shorter than the view ends at its last row
+        assert_eq!(view(3, 10).apply(Command::ViewBottom, None).selected, 2);
+    }
+}
@@ -1,11 +1,13 @@
 use anyhow::{Context, Result};
 use rspotify::prelude::*;
-use rspotify::{scopes, AuthCodePkceSpotify, Credentials, OAuth};
-use std::path::PathBuf;
+use rspotify::{AuthCodePkceSpotify, CallbackError, Credentials, OAuth, Token, TokenCallback};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::config::AppConfig;
 
-const REDIRECT_URI: &str = "http://127.0.0.1:8888/callback";
 const SCOPES: &[&str] = &[
     "user-read-playback-state",
     "user-modify-playback-state",
@@ -16,97 +18,432 @@ const SCOPES: &[&str] = &[
     "playlist-read-collaborative",
 ];
 
-fn token_cache_path() -> Result<PathBuf> {
-    Ok(AppConfig::config_dir()?.join(".spotify_token_cache.json"))
+// How long to wait for the browser to come back with the authorization code
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Spotify has no token revocation endpoint, so app access has to be
+/// removed from the account page.
+const ACCOUNT_APPS_URL: &str = "https://www.spotify.com/account/apps/";
+
+/// Writes the token cache readable only by the current user.
+fn write_token_cache(path: &Path, token: &Token) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to write token cache: {}", path.display()))?;
+
+    // `mode` only applies on creation, so tighten caches written by older versions
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    file.write_all(serde_json::to_string_pretty(token)?.as_bytes())?;
+    Ok(())
+}
+
+fn read_token_cache(path: &Path) -> Option<Token> {
+    let data = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
 }
 
-pub async fn authenticate() -> Result<AuthCodePkceSpotify> {
-    let config = AppConfig::load()?;
+/// How the authorization code gets back to crabify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginFlow {
+    /// Open a browser and catch the redirect with a local callback server.
+    Browser,
+    /// Print the authorization URL and read the redirected URL from stdin,
+    /// for sessions where the browser runs on another machine.
+    Headless,
+}
+
+impl LoginFlow {
+    /// Uses the headless flow when asked to (flag, config or env) or when
+    /// running over SSH without a display to open a browser on.
+    pub fn detect(headless: bool, config: &AppConfig) -> Self {
+        let over_ssh = std::env::var_os("SSH_CONNECTION").is_some()
+            && std::env::var_os("DISPLAY").is_none()
+            && std::env::var_os("WAYLAND_DISPLAY").is_none();
+        if headless || config.headless || over_ssh {
+            LoginFlow::Headless
+        } else {
+            LoginFlow::Browser
+        }
+    }
+}
+
+pub async fn authenticate(
+    config: &AppConfig,
+    headless: bool,
+) -> Result<(AuthCodePkceSpotify, LoginFlow)> {
+    let flow = LoginFlow::detect(headless, config);
+    let mut spotify = build_client(config, flow)?;
+
+    if !restore_session(&spotify, &config.token_cache_path()?).await {
+        authorize(&mut spotify, flow).await?;
+    }
+
+    Ok((spotify, flow))
+}
+
+/// Authenticates while the TUI owns the terminal, so a headless login
+/// prompt isn't possible; the browser flow still is, with its URL handed
+/// to `show_url` rather than printed.
+pub async fn authenticate_in_app(
+    config: &AppConfig,
+    show_url: impl FnOnce(String),
+) -> Result<(AuthCodePkceSpotify, LoginFlow)> {
+    let flow = LoginFlow::detect(false, config);
+    let mut spotify = build_client(config, flow)?;
+
+    if !restore_session(&spotify, &config.token_cache_path()?).await {
+        if flow == LoginFlow::Headless {
+            anyhow::bail!(
+                "profile '{}' isn't logged in. Run `crabify --profile {} --headless` once first.",
+                config.profile_name(),
+                config.profile_name()
+            );
+        }
+        authorize_in_app(&mut spotify, show_url).await?;
+    }
+
+    Ok((spotify, flow))
+}
+
+fn build_client(config: &AppConfig, flow: LoginFlow) -> Result<AuthCodePkceSpotify> {
+    // Fail early on a bad URI rather than after the user has logged in
+    if flow == LoginFlow::Browser {
+        callback_address(config.redirect_uri())?;
+    }
 
     let creds = Credentials::new_pkce(&config.client_id);
 
     let oauth = OAuth {
-        redirect_uri: REDIRECT_URI.to_string(),
-        scopes: scopes!(
-            "user-read-playback-state",
-            "user-modify-playback-state",
-            "user-read-currently-playing",
-            "user-library-read",
-            "user-library-modify",
-            "playlist-read-private",
-            "playlist-read-collaborative"
-        ),
+        redirect_uri: config.redirect_uri().to_string(),
+        scopes: SCOPES.iter().map(|s| s.to_string()).collect(),
         ..Default::default()
     };
 
+    // rspotify's own cache writer uses default permissions, so tokens are
+    // persisted through the callback instead (on login and on every refresh)
+    let callback_path = config.token_cache_path()?;
+    let token_callback = TokenCallback(Box::new(move |token: Token| {
+        write_token_cache(&callback_path, &token)
+            .map_err(|e| CallbackError::CustomizedError(e.to_string()))
+    }));
+
     let config = rspotify::Config {
-        token_cached: true,
-        cache_path: token_cache_path()?,
+        token_cached: false,
         token_refreshing: true,
+        token_callback_fn: Arc::new(Some(token_callback)),
         ..Default::default()
     };
 
-    let mut spotify = AuthCodePkceSpotify::with_config(creds, oauth.clone(), config);
+    Ok(AuthCodePkceSpotify::with_config(creds, oauth, config))
+}
+
+/// Loads the cached token, refreshing it if it has expired. Returns whether
+/// the client is ready to use without logging in.
+async fn restore_session(spotify: &AuthCodePkceSpotify, token_path: &Path) -> bool {
+    let Some(token) = read_token_cache(token_path) else {
+        return false;
+    };
+    if !spotify.oauth.scopes.is_subset(&token.scopes) {
+        return false;
+    }
+
+    let expired = token.is_expired();
+    let refreshable = token.refresh_token.is_some();
+    *spotify.token.lock().await.unwrap() = Some(token);
 
-    // Try to load cached token
-    let token_path = token_cache_path()?;
+    !expired || (refreshable && spotify.refresh_token().await.is_ok())
+}
+
+/// Deletes the cached token.
+pub fn logout(config: &AppConfig) -> Result<()> {
+    let token_path = config.token_cache_path()?;
     if token_path.exists() {
-        // Attempt to read cached token
-        let token_data = std::fs::read_to_string(&token_path).ok();
-        if let Some(data) = token_data {
-            if let Ok(token) = serde_json::from_str::<rspotify::Token>(&data) {
-                *spotify.token.lock().await.unwrap() = Some(token);
-                // Try a simple API call to verify the token works
-                if spotify.current_user().await.is_ok() {
-                    return Ok(spotify);
-                }
-                // Token is invalid, proceed with fresh auth
-            }
-        }
+        std::fs::remove_file(&token_path)
+            .with_context(|| format!("Failed to remove token cache: {}", token_path.display()))?;
+        eprintln!("Removed cached token at {}", token_path.display());
+    } else {
+        eprintln!("No cached token at {}", token_path.display());
     }
+    eprintln!(
+        "To revoke crabify's access to your account entirely, remove it at {}",
+        ACCOUNT_APPS_URL
+    );
+    Ok(())
+}
 
+/// Runs the OAuth PKCE flow and caches the resulting token.
+pub async fn authorize(spotify: &mut AuthCodePkceSpotify, flow: LoginFlow) -> Result<()> {
     // Perform fresh OAuth PKCE flow
     let auth_url = spotify.get_authorize_url(None)?;
+    let redirect_uri = spotify.oauth.redirect_uri.clone();
+    let state = spotify.oauth.state.clone();
 
-    // Start local HTTP server for callback
-    let server = tiny_http::Server::http("127.0.0.1:8888")
-        .map_err(|e| anyhow::anyhow!("Failed to start callback server: {}", e))?;
+    let code = match flow {
+        LoginFlow::Browser => {
+            let server = listen_for_callback(&redirect_uri)?;
+            eprintln!("Opening browser for Spotify authentication...");
+            if open::that(&auth_url).is_err() {
+                eprintln!("Could not open browser. Please visit this URL manually:\n{}", auth_url);
+            }
+            wait_for_code(&server, &redirect_uri, &state)?
+        }
+        LoginFlow::Headless => read_code_from_terminal(&auth_url, &redirect_uri, &state)?,
+    };
 
-    // Open browser for authentication
-    eprintln!("Opening browser for Spotify authentication...");
-    if open::that(&auth_url).is_err() {
-        eprintln!("Could not open browser. Please visit this URL manually:\n{}", auth_url);
+    // Exchange code for token (the token callback caches it)
+    spotify.request_token(&code).await?;
+
+    Ok(())
+}
+
+/// Runs the browser flow while the TUI is up: the URL goes to `show_url`
+/// instead of the terminal, and the wait for the callback runs on a
+/// thread of its own so the runtime keeps going, and quitting doesn't wait
+/// for it.
+pub async fn authorize_in_app(
+    spotify: &mut AuthCodePkceSpotify,
+    show_url: impl FnOnce(String),
+) -> Result<()> {
+    let auth_url = spotify.get_authorize_url(None)?;
+    let redirect_uri = spotify.oauth.redirect_uri.clone();
+    let state = spotify.oauth.state.clone();
+
+    let server = listen_for_callback(&redirect_uri)?;
+    let _ = open::that(&auth_url);
+    show_url(auth_url);
+    let (code_tx, code_rx) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = code_tx.send(wait_for_code(&server, &redirect_uri, &state));
+    });
+    let code = code_rx.await.context("The login callback server stopped")??;
+
+    spotify.request_token(&code).await?;
+    Ok(())
+}
+
+/// The `host:port` the callback server listens on, taken from the redirect URI.
+fn callback_address(redirect_uri: &str) -> Result<String> {
+    let url = url::Url::parse(redirect_uri)
+        .with_context(|| format!("Invalid redirect URI: {}", redirect_uri))?;
+    if url.scheme() != "http" {
+        anyhow::bail!(
+            "Invalid redirect URI: {} (the login callback server only speaks http)",
+            redirect_uri
+        );
     }
+    let host = url
+        .host_str()
+        .with_context(|| format!("Invalid redirect URI: {} (missing host)", redirect_uri))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    Ok(format!("{}:{}", host, port))
+}
+
+/// Starts the local server the browser is redirected to after login.
+fn listen_for_callback(redirect_uri: &str) -> Result<tiny_http::Server> {
+    let address = callback_address(redirect_uri)?;
+    tiny_http::Server::http(&address).map_err(|e| {
+        anyhow::anyhow!(
+            "Could not listen on {} for the Spotify login callback: {}\n\
+             If the port is in use, set SPOTIFY_REDIRECT_URI (or \"redirect_uri\" in the \
+             config) to a free port and register that URI in your Spotify app.",
+            address,
+            e
+        )
+    })
+}
+
+/// Waits for the browser to come back with the authorization code. Blocks.
+fn wait_for_code(server: &tiny_http::Server, redirect_uri: &str, state: &str) -> Result<String> {
+    // Wait for the callback, skipping unrelated requests such as favicons
+    let base = url::Url::parse(redirect_uri)?;
+    let deadline = Instant::now() + CALLBACK_TIMEOUT;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let request = server
+            .recv_timeout(remaining)
+            .context("Failed to receive OAuth callback")?
+            .with_context(|| {
+                format!(
+                    "Timed out after {} minutes waiting for the Spotify login callback.\n\
+                     If the browser can't reach {}, run `crabify --headless` and paste \
+                     the redirected URL instead.",
+                    CALLBACK_TIMEOUT.as_secs() / 60,
+                    redirect_uri
+                )
+            })?;
 
-    // Wait for the callback
-    let request = server
-        .recv()
-        .context("Failed to receive OAuth callback")?;
+        let parsed = match base.join(request.url()) {
+            Ok(parsed) if parsed.path() == base.path() => parsed,
+            _ => {
+                let _ = request.respond(html_page("Not found", "").with_status_code(404));
+                continue;
+            }
+        };
 
-    let url = format!("http://127.0.0.1:8888{}", request.url());
-    let parsed = url::Url::parse(&url).context("Failed to parse callback URL")?;
+        match parse_callback(&parsed, Some(state)) {
+            Ok(code) => {
+                let _ = request.respond(html_page(
+                    "Authentication successful!",
+                    "You can close this tab and return to the terminal.",
+                ));
+                return Ok(code);
+            }
+            Err(CallbackFailure::StateMismatch) => {
+                // Most likely a stale tab from an earlier attempt; keep waiting
+                let _ = request.respond(
+                    html_page(
+                        "Login expired",
+                        "This login link is from an earlier attempt. \
+                         Use the tab crabify opened most recently.",
+                    )
+                    .with_status_code(400),
+                );
+            }
+            Err(failure) => {
+                let message = failure.to_string();
+                let _ = request.respond(
+                    html_page("Authentication failed", &message).with_status_code(400),
+                );
+                anyhow::bail!(message);
+            }
+        }
+    }
+}
 
-    let code = parsed
-        .query_pairs()
-        .find(|(key, _)| key == "code")
-        .map(|(_, value)| value.to_string())
-        .context("No authorization code in callback")?;
+fn html_page(title: &str, message: &str) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    tiny_http::Response::from_string(format!(
+        "<html><body><h1>{}</h1><p>{}</p></body></html>",
+        escape(title),
+        escape(message)
+    ))
+    .with_header("Content-Type: text/html".parse::<tiny_http::Header>().unwrap())
+}
 
-    // Send response to browser
-    let response = tiny_http::Response::from_string(
-        "<html><body><h1>Authentication successful!</h1><p>You can close this tab and return to the terminal.</p></body></html>"
-    )
-    .with_header("Content-Type: text/html".parse::<tiny_http::Header>().unwrap());
-    let _ = request.respond(response);
+fn read_code_from_terminal(auth_url: &str, redirect_uri: &str, state: &str) -> Result<String> {
+    eprintln!(
+        "Open this URL in a browser on any machine and log in to Spotify:\n\n{}\n",
+        auth_url
+    );
+    eprintln!(
+        "Your browser will then be redirected to {} (the page will fail to load; that's expected).",
+        redirect_uri
+    );
+    eprint!("Paste the full URL from the address bar (or just the code) here: ");
+    std::io::stderr().flush()?;
 
-    // Exchange code for token
-    spotify.request_token(&code).await?;
+    let mut input = String::new();
+    std::io::stdin()
+        .read_line(&mut input)
+        .context("Failed to read the redirected URL")?;
+    let input = input.trim();
+
+    if input.contains('?') || input.contains('=') {
+        // Accept absolute URLs as well as `/callback?code=...` or `?code=...`
+        let parsed = url::Url::parse(redirect_uri)?
+            .join(input)
+            .context("Failed to parse the redirected URL")?;
+        // A hand-trimmed URL may have lost the state; only check it when present
+        let has_state = parsed.query_pairs().any(|(key, _)| key == "state");
+        Ok(parse_callback(&parsed, has_state.then_some(state))?)
+    } else if input.is_empty() {
+        anyhow::bail!("No authorization code entered")
+    } else {
+        Ok(input.to_string())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum CallbackFailure {
+    #[error("Spotify login was cancelled: access was denied.")]
+    AccessDenied,
+
+    #[error("Spotify login failed: {0}")]
+    Spotify(String),
+
+    #[error("The login response doesn't match this login attempt (state mismatch).")]
+    StateMismatch,
+
+    #[error("No authorization code in callback")]
+    MissingCode,
+}
+
+/// Extracts the authorization code from a redirect, checking the `state`
+/// parameter against the one sent in the authorization URL.
+fn parse_callback(url: &url::Url, expected_state: Option<&str>) -> Result<String, CallbackFailure> {
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+    };
+
+    if let Some(error) = param("error") {
+        return Err(match error.as_str() {
+            "access_denied" => CallbackFailure::AccessDenied,
+            _ => CallbackFailure::Spotify(error),
+        });
+    }
+    if let Some(expected) = expected_state {
+        if param("state").as_deref() != Some(expected) {
+            return Err(CallbackFailure::StateMismatch);
+        }
+    }
+    param("code").ok_or(CallbackFailure::MissingCode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str, expected_state: Option<&str>) -> Result<String, CallbackFailure> {
+        let url = url::Url::parse(&format!("http://127.0.0.1:8888/callback?{}", query)).unwrap();
+        parse_callback(&url, expected_state)
+    }
 
-    // Cache the token
-    if let Some(token) = spotify.token.lock().await.unwrap().as_ref() {
-        let token_json = serde_json::to_string_pretty(token)?;
-        std::fs::write(token_cache_path()?, token_json)?;
+    #[test]
+    fn returns_the_code_when_the_state_matches() {
+        assert_eq!(parse("code=abc&state=xyz", Some("xyz")).unwrap(), "abc");
+        assert_eq!(parse("code=a%2Bb", None).unwrap(), "a+b");
     }
 
-    Ok(spotify)
+    #[test]
+    fn rejects_a_missing_or_different_state() {
+        let different = parse("code=abc&state=other", Some("xyz"));
+        assert!(matches!(different, Err(CallbackFailure::StateMismatch)));
+        let missing = parse("code=abc", Some("xyz"));
+        assert!(matches!(missing, Err(CallbackFailure::StateMismatch)));
+    }
+
+    #[test]
+    fn reports_errors_before_checking_the_state() {
+        let denied = parse("error=access_denied&state=other", Some("xyz"));
+        assert!(matches!(denied, Err(CallbackFailure::AccessDenied)));
+        let other = parse("error=server_error&state=xyz", Some("xyz"));
+        assert!(matches!(other, Err(CallbackFailure::Spotify(e)) if e == "server_error"));
+    }
+
+    #[test]
+    fn needs_a_code() {
+        assert!(matches!(parse("state=xyz", Some("xyz")), Err(CallbackFailure::MissingCode)));
+    }
 }
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use rspotify::model::{FullTrack, SavedTrack, SimplifiedPlaylist};
+use rspotify::prelude::Id;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::config::AppConfig;
+
+// Upper bound for cached playlist tracks; least recently written go first
+const MAX_TRACK_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct CachedPlaylistTracks {
+    snapshot_id: String,
+    tracks: Vec<FullTrack>,
+}
+
+/// On-disk copy of the user's library, so the UI can render before the
+/// network responds.
+pub struct LibraryCache {
+    dir: PathBuf,
+}
+
+impl LibraryCache {
+    /// Opens the cache for the config's profile.
+    pub fn open(config: &AppConfig) -> Result<Self> {
+        let dir = config.library_cache_dir()?;
+        std::fs::create_dir_all(dir.join("tracks"))?;
+        Ok(Self { dir })
+    }
+
+    /// Removes the cached libraries of every profile.
+    pub fn clear() -> Result<()> {
+        let dir = AppConfig::config_dir()?.join("cache");
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)
+                .with_context(|| format!("Failed to remove cache: {}", dir.display()))?;
+        }
+        Ok(())
+    }
+
+    pub fn load_playlists(&self) -> Option<Vec<SimplifiedPlaylist>> {
+        read_json(&self.dir.join("playlists.json"))
+    }
+
+    pub fn save_playlists(&self, playlists: &[SimplifiedPlaylist]) -> Result<()> {
+        write_json(&self.dir.join("playlists.json"), &playlists)
+    }
+
+    pub fn load_liked_songs(&self) -> Option<Vec<SavedTrack>> {
+        read_json(&self.dir.join("liked_songs.json"))
+    }
+
+    pub fn save_liked_songs(&self, songs: &[SavedTrack]) -> Result<()> {
+        write_json(&self.dir.join("liked_songs.json"), &songs)
+    }
+
+    /// Puts a newly liked track at the top of the cached liked songs.
+    pub fn add_liked_song(&self, track: FullTrack) -> Result<()> {
+        let Some(mut songs) = self.load_liked_songs() else {
+            return Ok(());
+        };
+        let id = track.id.as_ref().map(|id| id.id().to_string()).unwrap_or_default();
+        songs.retain(|saved| !is_track(saved, &id));
+        let saved = SavedTrack {
+            added_at: chrono::Utc::now(),
+            track,
+        };
+        songs.insert(0, saved);
+        self.save_liked_songs(&songs)
+    }
+
+    /// Drops an unliked track from the cached liked songs.
+    pub fn remove_liked_song(&self, track_id: &str) -> Result<()> {
+        let Some(mut songs) = self.load_liked_songs() else {
+            return Ok(());
+        };
+        songs.retain(|saved| !is_track(saved, track_id));
+        self.save_liked_songs(&songs)
+    }
+
+    /// Returns the cached tracks only if they were saved for the same
+    /// playlist snapshot.
+    pub fn load_playlist_tracks(
+        &self,
+        playlist_id: &str,
+        snapshot_id: &str,
+    ) -> Option<Vec<FullTrack>> {
+        let cached: CachedPlaylistTracks = read_json(&self.tracks_path(playlist_id))?;
+        (cached.snapshot_id == snapshot_id).then_some(cached.tracks)
+    }
+
+    pub fn save_playlist_tracks(
+        &self,
+        playlist_id: &str,
+        snapshot_id: &str,
+        tracks: &[FullTrack],
+    ) -> Result<()> {
+        let cached = CachedPlaylistTracks {
+            snapshot_id: snapshot_id.to_string(),
+            tracks: tracks.to_vec(),
+        };
+        write_json(&self.tracks_path(playlist_id), &cached)?;
+        self.enforce_size_limit()
+    }
+
+    fn tracks_path(&self, playlist_id: &str) -> PathBuf {
+        // Accept both bare IDs and `spotify:playlist:` URIs
+        let id = playlist_id.rsplit(':').next().unwrap_or(playlist_id);
+        self.dir.join("tracks").join(format!("{}.json", id))
+    }
+
+    fn enforce_size_limit(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(self.dir.join("tracks"))? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            entries.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in entries {
+            if total <= MAX_TRACK_CACHE_BYTES {
+                break;
+            }
+            std::fs::remove_file(&path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `saved` is the track with `track_id`, a bare ID or a URI.
+fn is_track(saved: &SavedTrack, track_id: &str) -> bool {
+    let bare = track_id.rsplit(':').next().unwrap_or(track_id);
+    saved.track.id.as_ref().is_some_and(|id| id.id() == bare)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let json = serde_json::to_string(value)?;
+    std::fs::write(path, json)
+        .with_context(|| format!("Failed to write cache file: {}", path.display()))
+}
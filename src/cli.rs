@@ -0,0 +1,392 @@
+use anyhow::{bail, Context, Result};
+use rspotify::model::{CurrentPlaybackContext, FullTrack, PlayableItem, RepeatState};
+use serde_json::{json, Value};
+
+use crate::app::format_duration;
+use crate::auth;
+use crate::config::AppConfig;
+#[cfg(unix)]
+use crate::socket::{self, Request};
+use crate::spotify::SpotifyClient;
+use crate::statusline;
+
+/// A one-shot command run from the shell instead of the TUI, e.g. from a
+/// window manager hotkey.
+#[derive(Debug)]
+pub enum CliCommand {
+    Play,
+    Pause,
+    Toggle,
+    Next,
+    Prev,
+    Volume(VolumeChange),
+    Status { json: bool },
+    Search { query: String, play: bool },
+    Like,
+    Unlike,
+    ToggleLike,
+    /// Prints a line for status bars whenever playback changes.
+    Statusline { waybar: bool, format: Option<String> },
+    /// Lists devices, or transfers playback to the one named.
+    Device(Option<String>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum VolumeChange {
+    Set(u8),
+    Up(u8),
+    Down(u8),
+}
+
+impl std::str::FromStr for VolumeChange {
+    type Err = anyhow::Error;
+
+    /// Parses `50`, `+10` or `-10`.
+    fn from_str(s: &str) -> Result<Self> {
+        let percent = |n: &str| {
+            n.parse::<u8>()
+                .ok()
+                .filter(|n| *n <= 100)
+                .with_context(|| format!("volume must be 0-100, +N or -N, not `{}`", s))
+        };
+        match (s.strip_prefix('+'), s.strip_prefix('-')) {
+            (Some(n), _) => Ok(VolumeChange::Up(percent(n)?)),
+            (_, Some(n)) => Ok(VolumeChange::Down(percent(n)?)),
+            _ => Ok(VolumeChange::Set(percent(s)?)),
+        }
+    }
+}
+
+pub const USAGE: &str = "\
+Usage: crabify [--headless] [--profile <name>] [command]
+
+Without a command, starts the TUI. Commands:
+  play | pause | toggle      Resume, pause or toggle playback
+  next | prev                Skip to the next or previous track
+  volume <0-100|+N|-N>       Set or change the volume
+  status [--json]            Show what's playing
+  search <query> [--play]    Search for tracks, or play the top result
+  like | unlike | toggle-like
+                             Like or unlike the playing track
+  statusline [--waybar] [--format <template>]
+                             Print a line for status bars on every change
+  device [<name>]            List devices, or transfer playback to one
+  logout                     Log out of Spotify
+  cache clear                Clear the library cache
+  config init                Write a commented default config file";
+
+/// Parses a playback command, or returns `None` if `args` isn't one.
+pub fn parse(args: &[&str]) -> Result<Option<CliCommand>> {
+    let command = match args {
+        ["play"] => CliCommand::Play,
+        ["pause"] => CliCommand::Pause,
+        ["toggle"] => CliCommand::Toggle,
+        ["next"] => CliCommand::Next,
+        ["prev"] => CliCommand::Prev,
+        ["volume", change] => CliCommand::Volume(change.parse()?),
+        ["status"] => CliCommand::Status { json: false },
+        ["status", "--json"] => CliCommand::Status { json: true },
+        ["search", rest @ ..] => {
+            let play = rest.contains(&"--play");
+            let words: Vec<&str> = rest.iter().copied().filter(|w| *w != "--play").collect();
+            if words.is_empty() {
+                bail!("search needs a query");
+            }
+            CliCommand::Search {
+                query: words.join(" "),
+                play,
+            }
+        }
+        ["like"] => CliCommand::Like,
+        ["unlike"] => CliCommand::Unlike,
+        ["toggle-like"] => CliCommand::ToggleLike,
+        ["statusline", rest @ ..] => {
+            let mut waybar = false;
+            let mut format = None;
+            let mut flags = rest.iter();
+            while let Some(flag) = flags.next() {
+                match *flag {
+                    "--waybar" => waybar = true,
+                    "--format" => {
+                        let template = flags.next().context("--format needs a template")?;
+                        format = Some(template.to_string());
+                    }
+                    flag => bail!("unknown statusline option `{}`", flag),
+                }
+            }
+            CliCommand::Statusline { waybar, format }
+        }
+        ["device"] => CliCommand::Device(None),
+        ["device", name @ ..] => CliCommand::Device(Some(name.join(" "))),
+        _ => return Ok(None),
+    };
+    Ok(Some(command))
+}
+
+impl CliCommand {
+    /// The same command for a running instance, if it can take it.
+    #[cfg(unix)]
+    fn request(&self) -> Option<Request> {
+        let request = match *self {
+            CliCommand::Play => Request::Play,
+            CliCommand::Pause => Request::Pause,
+            CliCommand::Toggle => Request::Toggle,
+            CliCommand::Next => Request::Next,
+            CliCommand::Prev => Request::Prev,
+            CliCommand::Volume(VolumeChange::Set(volume)) => Request::Volume { volume },
+            CliCommand::Volume(VolumeChange::Up(by)) => Request::ChangeVolume { by: by as i32 },
+            CliCommand::Volume(VolumeChange::Down(by)) => Request::ChangeVolume {
+                by: -(by as i32),
+            },
+            CliCommand::Status { .. } => Request::Status,
+            CliCommand::Like => Request::Like,
+            CliCommand::Unlike => Request::Unlike,
+            CliCommand::ToggleLike => Request::ToggleLike,
+            CliCommand::Search { .. } | CliCommand::Device(_) | CliCommand::Statusline { .. } => {
+                return None
+            }
+        };
+        Some(request)
+    }
+}
+
+/// Runs `command` through the TUI if one is running for this profile, so
+/// the two don't race each other, or else against the API directly.
+pub async fn run(config: &AppConfig, headless: bool, command: CliCommand) -> Result<()> {
+    if let CliCommand::Statusline { waybar, format } = command {
+        let format = format.unwrap_or_else(|| config.statusline.format.clone());
+        if let Some(field) = statusline::unknown_field(&format) {
+            let fields = statusline::FIELDS.join(", ");
+            bail!("unknown field {{{}}}, expected one of: {}", field, fields);
+        }
+        return statusline::run(config, headless, waybar, &format).await;
+    }
+
+    #[cfg(unix)]
+    if let Some(request) = command.request() {
+        if let Some(reply) = socket::request(&config.socket_path()?, &request).await? {
+            if let CliCommand::Status { json } = command {
+                print_status(&reply["status"], json);
+            }
+            return Ok(());
+        }
+    }
+
+    let (spotify_auth, login_flow) = auth::authenticate(config, headless).await?;
+    let client = SpotifyClient::new(spotify_auth, login_flow, config.market());
+    run_with_client(&client, command).await
+}
+
+async fn run_with_client(client: &SpotifyClient, command: CliCommand) -> Result<()> {
+    match command {
+        CliCommand::Play => client.resume().await?,
+        CliCommand::Pause => client.pause().await?,
+        CliCommand::Toggle => {
+            let playing = client.fetch_now_playing().await?.is_some_and(|ctx| ctx.is_playing);
+            if playing {
+                client.pause().await?;
+            } else {
+                client.resume().await?;
+            }
+        }
+        CliCommand::Next => client.next_track().await?,
+        CliCommand::Prev => client.previous_track().await?,
+        CliCommand::Volume(change) => {
+            let current = || async {
+                let ctx = client.fetch_now_playing().await?;
+                let volume = ctx.and_then(|ctx| ctx.device.volume_percent);
+                volume.context("the active device doesn't report its volume")
+            };
+            let volume = match change {
+                VolumeChange::Set(volume) => volume,
+                VolumeChange::Up(step) => (current().await? as u8).saturating_add(step).min(100),
+                VolumeChange::Down(step) => (current().await? as u8).saturating_sub(step),
+            };
+            client.set_volume(volume).await?;
+        }
+        CliCommand::Status { json } => {
+            print_status(&fetch_status(client).await?, json);
+        }
+        CliCommand::Search { query, play } => {
+            let tracks = client.search_tracks(&query).await?;
+            if play {
+                let track = tracks.first().with_context(|| format!("no results for `{}`", query))?;
+                let uri = track.id.as_ref().context("the top result can't be played")?;
+                client.play_track(&uri.to_string()).await?;
+                println!("Playing {}", track_name(&track.name, &track.artists));
+            } else {
+                for track in &tracks {
+                    let uri = track.id.as_ref().map(|id| id.to_string()).unwrap_or_default();
+                    println!("{}\t{}", track_name(&track.name, &track.artists), uri);
+                }
+            }
+        }
+        CliCommand::Like | CliCommand::Unlike | CliCommand::ToggleLike => {
+            let ctx = client.fetch_now_playing().await?;
+            let track = match ctx.and_then(|ctx| ctx.item) {
+                Some(PlayableItem::Track(track)) => track,
+                _ => bail!("no track is playing"),
+            };
+            let id = track.id.as_ref().context("the playing track can't be liked")?;
+            let name = track_name(&track.name, &track.artists);
+            let like = match command {
+                CliCommand::ToggleLike => !client.is_saved(&id.to_string()).await?,
+                command => matches!(command, CliCommand::Like),
+            };
+            if like {
+                client.save_track(&id.to_string()).await?;
+                println!("Liked {}", name);
+            } else {
+                client.remove_track(&id.to_string()).await?;
+                println!("Unliked {}", name);
+            }
+        }
+        CliCommand::Statusline { .. } => unreachable!("statusline is handled by `run`"),
+        CliCommand::Device(None) => {
+            for device in client.fetch_devices().await? {
+                let marker = if device.is_active { "*" } else { " " };
+                println!("{} {} ({:?})", marker, device.name, device._type);
+            }
+        }
+        CliCommand::Device(Some(name)) => {
+            let devices = client.fetch_devices().await?;
+            // An exact name wins; otherwise a unique partial match will do
+            let wanted = name.to_lowercase();
+            let matching = |exact: bool| -> Vec<_> {
+                devices
+                    .iter()
+                    .filter(|d| {
+                        let name = d.name.to_lowercase();
+                        if exact {
+                            name == wanted
+                        } else {
+                            name.contains(&wanted)
+                        }
+                    })
+                    .collect()
+            };
+            let (exact, partial) = (matching(true), matching(false));
+            let device = match (exact.as_slice(), partial.as_slice()) {
+                ([device, ..], _) | ([], [device]) => *device,
+                ([], []) => {
+                    let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+                    bail!("no device named `{}`. Devices: {}", name, names.join(", "));
+                }
+                ([], matches) => {
+                    let names: Vec<&str> = matches.iter().map(|d| d.name.as_str()).collect();
+                    bail!("`{}` matches several devices: {}", name, names.join(", "));
+                }
+            };
+            let id = device.id.as_deref().context("that device can't be controlled")?;
+            client.transfer_playback(id).await?;
+            println!("Playing on {}", device.name);
+        }
+    }
+    Ok(())
+}
+
+fn track_name(name: &str, artists: &[rspotify::model::SimplifiedArtist]) -> String {
+    let artists: Vec<&str> = artists.iter().map(|a| a.name.as_str()).collect();
+    format!("{} - {}", name, artists.join(", "))
+}
+
+/// The status from the API, with whether the track playing is liked.
+pub async fn fetch_status(client: &SpotifyClient) -> Result<Value> {
+    let ctx = client.fetch_now_playing().await?;
+    let mut status = status_json(ctx.as_ref());
+    if let Some(uri) = status["item"]["uri"].as_str().filter(|uri| uri.contains(":track:")) {
+        status["liked"] = client.is_saved(uri).await?.into();
+    }
+    Ok(status)
+}
+
+fn print_status(status: &Value, json: bool) {
+    if json {
+        println!("{}", status);
+    } else {
+        println!("{}", status_line(status));
+    }
+}
+
+/// One line for humans from the JSON status.
+fn status_line(status: &Value) -> String {
+    let item = &status["item"];
+    let Some(name) = item["name"].as_str() else {
+        return "Nothing playing".to_string();
+    };
+    let by = match item["artists"].as_array() {
+        Some(artists) => {
+            let artists: Vec<&str> = artists.iter().filter_map(Value::as_str).collect();
+            artists.join(", ")
+        }
+        None => item["show"].as_str().unwrap_or_default().to_string(),
+    };
+    let icon = if status["is_playing"] == true { "▶" } else { "⏸" };
+    let progress_ms = status["progress_ms"].as_i64().unwrap_or(0);
+    let duration_ms = item["duration_ms"].as_i64().unwrap_or(0);
+    let device = &status["device"];
+    let volume = device["volume_percent"]
+        .as_u64()
+        .map(|v| format!("  Vol: {}%", v))
+        .unwrap_or_default();
+    format!(
+        "{} {} - {}  {} / {}{}  on {}",
+        icon,
+        name,
+        by,
+        format_duration(progress_ms),
+        format_duration(duration_ms),
+        volume,
+        device["name"].as_str().unwrap_or_default()
+    )
+}
+
+/// A track as it appears in the JSON status.
+pub fn track_json(track: &FullTrack) -> Value {
+    json!({
+        "type": "track",
+        "uri": track.id.as_ref().map(|id| id.to_string()),
+        "name": track.name,
+        "artists": track.artists.iter().map(|a| &a.name).collect::<Vec<_>>(),
+        "album": track.album.name,
+        "duration_ms": track.duration.num_milliseconds(),
+    })
+}
+
+/// A track or episode as it appears in the JSON status.
+pub fn item_json(item: &PlayableItem) -> Value {
+    match item {
+        PlayableItem::Track(track) => track_json(track),
+        PlayableItem::Episode(episode) => json!({
+            "type": "episode",
+            "uri": episode.id.to_string(),
+            "name": episode.name,
+            "show": episode.show.name,
+            "duration_ms": episode.duration.num_milliseconds(),
+        }),
+    }
+}
+
+/// The playback state as JSON, for `status --json` and the control socket.
+pub fn status_json(ctx: Option<&CurrentPlaybackContext>) -> Value {
+    let Some(ctx) = ctx else {
+        return json!({ "is_playing": false, "item": null });
+    };
+    let item = ctx.item.as_ref().map(item_json);
+    let repeat = match ctx.repeat_state {
+        RepeatState::Off => "off",
+        RepeatState::Context => "context",
+        RepeatState::Track => "track",
+    };
+    json!({
+        "is_playing": ctx.is_playing,
+        "item": item,
+        "progress_ms": ctx.progress.map(|p| p.num_milliseconds()),
+        "shuffle": ctx.shuffle_state,
+        "repeat": repeat,
+        "device": {
+            "name": ctx.device.name,
+            "volume_percent": ctx.device.volume_percent,
+        },
+    })
+}
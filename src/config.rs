@@ -1,40 +1,757 @@
 use anyhow::{Context, Result};
+use rspotify::model::{Country, Market};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::app::Screen;
+use crate::hooks::Hook;
+use crate::keymap::{Command, KeyBindings, Keymap};
+use crate::statusline;
+use crate::ui::theme::{self, Theme};
+
+pub const DEFAULT_REDIRECT_URI: &str = "http://127.0.0.1:8888/callback";
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Written by `crabify config init`; every setting at its default value.
+const DEFAULT_CONFIG_TOML: &str = r#"# crabify configuration
+#
+# Environment variables (SPOTIFY_CLIENT_ID, SPOTIFY_REDIRECT_URI,
+# CRABIFY_TOKEN_CACHE, CRABIFY_HEADLESS) override the values in this file.
+
+# Client ID of your app in the Spotify Developer Dashboard.
+client_id = ""
+
+# Must match a redirect URI registered for that app. The login callback
+# server listens on its host and port.
+# redirect_uri = "http://127.0.0.1:8888/callback"
+
+# Where the OAuth token is cached (defaults to the config directory).
+# token_cache_path = "/path/to/token.json"
+
+# Log in by pasting the redirected URL instead of using a local browser.
+headless = false
+
+# How often the UI redraws and checks timers, in milliseconds (50-1000).
+tick_rate_ms = 250
+
+# How often to poll Spotify for the current track while playing, in seconds
+# (1-60). Polling backs off when paused or idle.
+poll_interval_secs = 5
+
+# Volume change per keypress, in percent (1-100).
+volume_step = 5
+
+# Screen shown on startup: "library", "search", "liked_songs" or "stats".
+default_screen = "library"
+
+# Color theme: "default", "high-contrast", "light" (for light terminal
+# backgrounds) or "monochrome". Setting NO_COLOR forces "monochrome".
+theme = "default"
+
+# Click tabs and rows (double-click plays), scroll lists and click the
+# progress bar to seek. Turn off to select text with the mouse instead.
+mouse = true
+
+# On Linux, show up as an MPRIS media player on the session bus so media
+# keys, status bars and `playerctl` can control playback.
+mpris = true
+
+# Listen on a local socket so `crabify play`, `crabify status` and editor
+# plugins control this instance instead of calling Spotify separately.
+control_socket = true
+
+# Market used for track availability: "from_token" (your account's
+# country) or an ISO 3166-1 alpha-2 country code such as "US".
+market = "from_token"
+
+# Columns shown in each track table, in order. Available columns:
+# "number", "title", "artist", "album", "duration".
+[columns]
+playlist = ["number", "title", "artist", "duration"]
+search = ["number", "title", "artist", "album", "duration"]
+liked_songs = ["number", "title", "artist", "album", "duration"]
+
+# Output of `crabify statusline`, for status bars. Fields: {icon} (play or
+# pause symbol), {state} ("playing" or "paused"), {title}, {artist},
+# {album}, {duration}, {volume}, {device}, {heart} (filled if liked),
+# {liked} (a heart only if liked), {shuffle} ("on" or "off") and {repeat}
+# ("off", "context" or "track").
+[statusline]
+format = "{icon} {artist} - {title} {liked}"
+# Printed instead when nothing is playing.
+idle = ""
+
+# Shell commands to run when playback changes. Each gets the event and
+# track in CRABIFY_EVENT, CRABIFY_TITLE, CRABIFY_ARTIST, CRABIFY_ALBUM,
+# CRABIFY_URI, CRABIFY_DURATION_MS, CRABIFY_DEVICE, CRABIFY_IS_PLAYING and
+# CRABIFY_LIKED, and the same status as `crabify status --json` on stdin.
+[hooks]
+# track_changed = 'notify-send "$CRABIFY_TITLE" "$CRABIFY_ARTIST"'
+# paused = "..."
+# resumed = "..."
+# track_liked = "..."
+# device_changed = "..."
+
+# On Linux, a desktop notification with the title, artist and album when
+# the track changes. Polling Spotify normally pauses while the terminal is
+# in the background; this keeps it going so no track change is missed.
+[notifications]
+enabled = false
+# Also notify while the terminal has focus. Needed for terminals that
+# don't report focus.
+when_focused = false
+# At most one notification this often; skipping through tracks quickly
+# only shows the last one.
+min_interval_secs = 5
+
+# A web remote and JSON API for other devices on the network, e.g. phones
+# controlling a shared speaker. Anyone who can reach the address can
+# control playback, so listening beyond this machine (e.g. on
+# "0.0.0.0:8787") needs a token.
+[http]
+enabled = false
+listen = "127.0.0.1:8787"
+# Required as `?token=...` or an `Authorization: Bearer ...` header.
+token = ""
+
+# A party queue on the web remote: guests suggest tracks and vote, instead
+# of playing and queueing them directly, and the most wanted suggestion is
+# queued on Spotify as each track nears its end.
+[party]
+enabled = false
+# How many tracks each guest (by IP address) can suggest per hour.
+suggestions_per_hour = 5
+# How long before the end of a track to queue the next suggestion.
+queue_before_end_secs = 15
+
+# Submit listens to ListenBrainz (or a compatible server) once a track has
+# played for half its length or 4 minutes. Listens that can't be sent yet
+# are kept and retried, across restarts.
+[scrobble]
+enabled = false
+# Your user token, from https://listenbrainz.org/settings/
+token = ""
+url = "https://api.listenbrainz.org"
+
+# Keep a log of the tracks crabify sees playing, for the Stats screen.
+# Stays in the config directory. Tracks that start and end while polling
+# is paused for an unfocused terminal aren't seen.
+[history]
+enabled = true
+
+# Key bindings, by command. Binding a command replaces its default keys;
+# an empty list unbinds it. Keys are names ("enter", "space", "tab", "esc",
+# "up", "pagedown", "f1", ...) or characters, optionally with "ctrl+",
+# "alt+" or "shift+". Characters typed together form a chord, like "gg";
+# separate keys with spaces for chords with modifiers, like "ctrl+w j".
+# The help overlay (?) shows the keys currently in effect.
+#
+# Commands: quit, help, next_screen, prev_screen, move_down, move_up,
+# half_page_down, half_page_up, page_down, page_up, go_to_top, go_to_bottom,
+# view_top, view_middle, view_bottom, jump_to_playing, select, back, search,
+# play_pause, next_track, previous_track, volume_up, volume_down, toggle_like,
+# toggle_like_now_playing, devices, accounts, party_queue, remove, stats,
+# palette
+#
+# Motions (move_down through view_bottom) take a count typed before them,
+# like "5j"; with go_to_top or go_to_bottom, "12G" goes to row 12.
+[keys]
+# next_track = ["n", "ctrl+right"]
+# quit = "ctrl+q"
+
+# Named accounts. Each can override client_id, token_cache_path,
+# redirect_uri, headless and market, and gets its own token, library cache
+# and listening history. Every other setting, like the theme, keys, hooks
+# and scrobbling, is shared by all profiles.
+# Select one with `crabify --profile work`.
+# [profiles.work]
+# client_id = "..."
+"#;
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct AppConfig {
     pub client_id: String,
+
+    /// Overrides where the OAuth token is cached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_cache_path: Option<PathBuf>,
+
+    /// Log in by pasting the redirected URL instead of using a local browser.
+    pub headless: bool,
+
+    /// Must match a redirect URI registered for the Spotify app. The login
+    /// callback server listens on its host and port.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_uri: Option<String>,
+
+    pub tick_rate_ms: u64,
+    pub poll_interval_secs: u64,
+    pub volume_step: u8,
+    pub default_screen: Screen,
+    pub theme: String,
+
+    /// Capture the mouse for clicking and scrolling.
+    pub mouse: bool,
+
+    /// Serve the MPRIS D-Bus interface (Linux only).
+    pub mpris: bool,
+
+    /// Listen on a Unix socket for commands from scripts (Unix only).
+    pub control_socket: bool,
+
+    /// `from_token` or an ISO 3166-1 alpha-2 country code.
+    pub market: String,
+
+    pub columns: Columns,
+
+    pub statusline: StatuslineConfig,
+
+    pub hooks: HooksConfig,
+
+    pub notifications: NotificationsConfig,
+
+    pub http: HttpConfig,
+
+    pub party: PartyConfig,
+
+    pub scrobble: ScrobbleConfig,
+
+    pub history: HistoryConfig,
+
+    /// Key bindings by command name, replacing that command's defaults.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<Command, KeyBindings>,
+
+    /// Named accounts; each overrides some of the top-level settings above.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+
+    /// The profile these settings were resolved for, `None` for the default.
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            client_id: String::new(),
+            token_cache_path: None,
+            headless: false,
+            redirect_uri: None,
+            tick_rate_ms: 250,
+            poll_interval_secs: 5,
+            volume_step: 5,
+            default_screen: Screen::Library,
+            theme: "default".to_string(),
+            mouse: true,
+            mpris: true,
+            control_socket: true,
+            market: "from_token".to_string(),
+            columns: Columns::default(),
+            statusline: StatuslineConfig::default(),
+            hooks: HooksConfig::default(),
+            notifications: NotificationsConfig::default(),
+            http: HttpConfig::default(),
+            party: PartyConfig::default(),
+            scrobble: ScrobbleConfig::default(),
+            history: HistoryConfig::default(),
+            keys: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+            profile: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_cache_path: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headless: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_uri: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Number,
+    Title,
+    Artist,
+    Album,
+    Duration,
+}
+
+/// Which columns each track table shows, in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Columns {
+    pub playlist: Vec<Column>,
+    pub search: Vec<Column>,
+    pub liked_songs: Vec<Column>,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        use Column::*;
+        Self {
+            playlist: vec![Number, Title, Artist, Duration],
+            search: vec![Number, Title, Artist, Album, Duration],
+            liked_songs: vec![Number, Title, Artist, Album, Duration],
+        }
+    }
+}
+
+/// Templates for `crabify statusline`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatuslineConfig {
+    pub format: String,
+    /// Shown when nothing is playing.
+    pub idle: String,
+}
+
+impl Default for StatuslineConfig {
+    fn default() -> Self {
+        Self {
+            format: "{icon} {artist} - {title} {liked}".to_string(),
+            idle: String::new(),
+        }
+    }
+}
+
+/// Shell commands run on playback events, by event.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track_changed: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paused: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resumed: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track_liked: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_changed: Option<String>,
+}
+
+impl HooksConfig {
+    pub fn command(&self, hook: Hook) -> Option<&str> {
+        let command = match hook {
+            Hook::TrackChanged => &self.track_changed,
+            Hook::Paused => &self.paused,
+            Hook::Resumed => &self.resumed,
+            Hook::TrackLiked => &self.track_liked,
+            Hook::DeviceChanged => &self.device_changed,
+        };
+        command.as_deref().filter(|command| !command.trim().is_empty())
+    }
+}
+
+/// Desktop notifications on track changes (Linux only).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    pub enabled: bool,
+    /// Notify even while the terminal has focus.
+    pub when_focused: bool,
+    pub min_interval_secs: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            when_focused: false,
+            min_interval_secs: 5,
+        }
+    }
+}
+
+/// The web remote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    pub listen: String,
+    /// Empty for no token.
+    pub token: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:8787".to_string(),
+            token: String::new(),
+        }
+    }
+}
+
+/// Guest suggestions and voting on the web remote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartyConfig {
+    pub enabled: bool,
+    pub suggestions_per_hour: u32,
+    pub queue_before_end_secs: u64,
+}
+
+impl Default for PartyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            suggestions_per_hour: 5,
+            queue_before_end_secs: 15,
+        }
+    }
+}
+
+/// Submitting listens to ListenBrainz.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrobbleConfig {
+    pub enabled: bool,
+    pub token: String,
+    /// The API's base URL, for other ListenBrainz-compatible servers.
+    pub url: String,
+}
+
+impl Default for ScrobbleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token: String::new(),
+            url: "https://api.listenbrainz.org".to_string(),
+        }
+    }
+}
+
+/// The local listening history behind the Stats screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// The settings the TUI needs once authentication is done.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub tick_rate: Duration,
+    pub poll_interval: Duration,
+    pub volume_step: u8,
+    pub default_screen: Screen,
+    pub columns: Columns,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    /// Whether track changes are shown as desktop notifications, which
+    /// needs polling to go on while unfocused.
+    pub notifications: bool,
+    /// How long before the end of a track the party queue feeds Spotify's.
+    pub party_lead: Duration,
+    /// Whether listens are scrobbled, which also needs polling to go on
+    /// while unfocused.
+    pub scrobble: bool,
+    /// Whether plays are recorded for the Stats screen.
+    pub history: bool,
 }
 
 impl AppConfig {
-    pub fn load() -> Result<Self> {
+    /// Loads the settings for `profile` (or `CRABIFY_PROFILE`, or the
+    /// top-level settings if neither is given).
+    pub fn load(profile: Option<&str>) -> Result<Self> {
+        let config = Self::load_unvalidated(profile)?;
+        if config.client_id.is_empty() {
+            anyhow::bail!(
+                "Spotify Client ID not found for profile '{}'.\n\
+                 Set SPOTIFY_CLIENT_ID environment variable or set client_id in {}",
+                config.profile_name(),
+                Self::config_file_path()?.display()
+            )
+        }
+        Ok(config)
+    }
+
+    /// Reads the config file and environment without requiring a client ID,
+    /// for commands that don't talk to Spotify.
+    pub fn load_unvalidated(profile: Option<&str>) -> Result<Self> {
         // Load .env file if present (won't override existing env vars)
         let _ = dotenvy::dotenv();
 
-        // First check environment variable
+        let mut config = Self::read_file()?;
+
+        let env_profile = std::env::var("CRABIFY_PROFILE").ok();
+        let profile = profile
+            .or(env_profile.as_deref())
+            .map(str::trim)
+            .filter(|p| !p.is_empty() && *p != DEFAULT_PROFILE);
+        if let Some(name) = profile {
+            config.apply_profile(name)?;
+        }
+
+        // Environment variables take precedence over the config file,
+        // profiles included
         if let Ok(client_id) = std::env::var("SPOTIFY_CLIENT_ID") {
             let client_id = client_id.trim().to_string();
             if !client_id.is_empty() {
-                return Ok(Self { client_id });
+                config.client_id = client_id;
+            }
+        }
+        if let Ok(path) = std::env::var("CRABIFY_TOKEN_CACHE") {
+            if !path.trim().is_empty() {
+                config.token_cache_path = Some(PathBuf::from(path.trim()));
+            }
+        }
+        if let Ok(redirect_uri) = std::env::var("SPOTIFY_REDIRECT_URI") {
+            if !redirect_uri.trim().is_empty() {
+                config.redirect_uri = Some(redirect_uri.trim().to_string());
             }
         }
+        if let Ok(headless) = std::env::var("CRABIFY_HEADLESS") {
+            config.headless = matches!(headless.trim(), "1" | "true" | "yes");
+        }
+
+
+        Ok(config)
+    }
+
+    /// Reads `config.toml`, falling back to the older `config.json`.
+    fn read_file() -> Result<Self> {
+        let toml_path = Self::config_file_path()?;
+        let json_path = Self::config_dir()?.join("config.json");
+
+        let (path, is_toml) = if toml_path.exists() {
+            (toml_path, true)
+        } else if json_path.exists() {
+            (json_path, false)
+        } else {
+            return Ok(AppConfig::default());
+        };
+
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        let config: AppConfig = if is_toml {
+            toml::from_str(&contents).map_err(anyhow::Error::from)
+        } else {
+            serde_json::from_str(&contents).map_err(anyhow::Error::from)
+        }
+        .and_then(|config: AppConfig| config.validate().map(|_| config))
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Checks values that parse fine but make no sense, naming the key.
+    fn validate(&self) -> Result<()> {
+        fn check(ok: bool, key: &str, value: impl std::fmt::Display, expected: &str) -> Result<()> {
+            if ok {
+                Ok(())
+            } else {
+                anyhow::bail!("`{}` = {}: {}", key, value, expected)
+            }
+        }
+
+        check(
+            (50..=1000).contains(&self.tick_rate_ms),
+            "tick_rate_ms",
+            self.tick_rate_ms,
+            "must be between 50 and 1000",
+        )?;
+        check(
+            (1..=60).contains(&self.poll_interval_secs),
+            "poll_interval_secs",
+            self.poll_interval_secs,
+            "must be between 1 and 60",
+        )?;
+        check(
+            self.notifications.min_interval_secs <= 3600,
+            "notifications.min_interval_secs",
+            self.notifications.min_interval_secs,
+            "must be at most 3600",
+        )?;
+        let listen = self.http.listen.parse::<std::net::SocketAddr>();
+        check(
+            listen.is_ok(),
+            "http.listen",
+            format!("{:?}", self.http.listen),
+            "must be an address and port like \"0.0.0.0:8787\"",
+        )?;
+        check(
+            !self.http.enabled
+                || listen.is_ok_and(|addr| addr.ip().is_loopback())
+                || !self.http.token.trim().is_empty(),
+            "http.token",
+            "\"\"",
+            "needed to listen beyond this machine",
+        )?;
+        check(
+            (5..=120).contains(&self.party.queue_before_end_secs),
+            "party.queue_before_end_secs",
+            self.party.queue_before_end_secs,
+            "must be between 5 and 120",
+        )?;
+        check(
+            !self.scrobble.enabled || !self.scrobble.token.trim().is_empty(),
+            "scrobble.token",
+            "\"\"",
+            "needed when scrobbling is enabled",
+        )?;
+        check(
+            url::Url::parse(&self.scrobble.url).is_ok_and(|url| url.scheme().starts_with("http")),
+            "scrobble.url",
+            format!("{:?}", self.scrobble.url),
+            "must be an http(s) URL",
+        )?;
+        check(
+            (1..=100).contains(&self.volume_step),
+            "volume_step",
+            self.volume_step,
+            "must be between 1 and 100",
+        )?;
+        check(
+            theme::NAMES.contains(&self.theme.as_str()),
+            "theme",
+            format!("{:?}", self.theme),
+            &format!("unknown theme, expected one of: {}", theme::NAMES.join(", ")),
+        )?;
+        check(
+            parse_market(&self.market).is_some(),
+            "market",
+            format!("{:?}", self.market),
+            "expected \"from_token\" or an ISO 3166-1 alpha-2 country code",
+        )?;
+        for (name, profile) in &self.profiles {
+            // Names become file names for the token, caches and history
+            check(
+                valid_profile_name(name),
+                "profiles",
+                format!("{:?}", name),
+                "profile names can't be empty or contain `/`, `\\` or `..`",
+            )?;
+            if let Some(market) = &profile.market {
+                check(
+                    parse_market(market).is_some(),
+                    &format!("profiles.{}.market", name),
+                    format!("{:?}", market),
+                    "expected \"from_token\" or an ISO 3166-1 alpha-2 country code",
+                )?;
+            }
+        }
+        if let Err(e) = Keymap::new(&self.keys) {
+            anyhow::bail!("`keys`: {}", e);
+        }
+        for (table, columns) in [
+            ("columns.playlist", &self.columns.playlist),
+            ("columns.search", &self.columns.search),
+            ("columns.liked_songs", &self.columns.liked_songs),
+        ] {
+            check(!columns.is_empty(), table, "[]", "needs at least one column")?;
+        }
+        for (key, template) in [
+            ("statusline.format", &self.statusline.format),
+            ("statusline.idle", &self.statusline.idle),
+        ] {
+            if let Some(field) = statusline::unknown_field(template) {
+                anyhow::bail!(
+                    "`{}` = {:?}: unknown field {{{}}}, expected one of: {}",
+                    key,
+                    template,
+                    field,
+                    statusline::FIELDS.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            anyhow::bail!(
+                "Unknown profile '{}'. Available profiles: {}",
+                name,
+                self.profile_names().join(", ")
+            )
+        };
+
+        if let Some(client_id) = profile.client_id {
+            self.client_id = client_id;
+        }
+        // Never inherit the default token cache; that would log both
+        // profiles into the same account
+        self.token_cache_path = profile.token_cache_path;
+        if let Some(headless) = profile.headless {
+            self.headless = headless;
+        }
+        if let Some(redirect_uri) = profile.redirect_uri {
+            self.redirect_uri = Some(redirect_uri);
+        }
+        if let Some(market) = profile.market {
+            self.market = market;
+        }
+        self.profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Writes a commented default `config.toml`, refusing to overwrite one.
+    pub fn write_default() -> Result<PathBuf> {
+        let path = Self::config_file_path()?;
+        if path.exists() {
+            anyhow::bail!("Config file already exists: {}", path.display());
+        }
+        std::fs::write(&path, DEFAULT_CONFIG_TOML)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+        Ok(path)
+    }
 
-        // Then check config file
-        let config_path = Self::config_file_path()?;
-        if config_path.exists() {
-            let contents = std::fs::read_to_string(&config_path)
-                .with_context(|| format!("Failed to read config file: {}", config_path.display()))?;
-            let config: AppConfig = serde_json::from_str(&contents)
-                .with_context(|| "Failed to parse config file")?;
-            return Ok(config);
+    pub fn settings(&self) -> Settings {
+        Settings {
+            tick_rate: Duration::from_millis(self.tick_rate_ms),
+            poll_interval: Duration::from_secs(self.poll_interval_secs),
+            volume_step: self.volume_step,
+            default_screen: self.default_screen,
+            columns: self.columns.clone(),
+            // Validated on load
+            keymap: Keymap::new(&self.keys).unwrap_or_default(),
+            theme: Theme::load(&self.theme),
+            notifications: cfg!(target_os = "linux") && self.notifications.enabled,
+            party_lead: Duration::from_secs(self.party.queue_before_end_secs),
+            scrobble: self.scrobble.enabled,
+            history: self.history.enabled,
         }
+    }
+
+    pub fn market(&self) -> Market {
+        // Validated on load
+        parse_market(&self.market).unwrap_or(Market::FromToken)
+    }
+
+    pub fn profile_name(&self) -> &str {
+        self.profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
 
-        anyhow::bail!(
-            "Spotify Client ID not found.\n\
-             Set SPOTIFY_CLIENT_ID environment variable or create config at {}",
-            config_path.display()
-        )
+    /// The default profile followed by the named ones.
+    pub fn profile_names(&self) -> Vec<String> {
+        std::iter::once(DEFAULT_PROFILE.to_string())
+            .chain(self.profiles.keys().cloned())
+            .collect()
     }
 
     pub fn config_dir() -> Result<PathBuf> {
@@ -45,7 +762,119 @@ impl AppConfig {
         Ok(dir)
     }
 
-    fn config_file_path() -> Result<PathBuf> {
-        Ok(Self::config_dir()?.join("config.json"))
+    pub fn config_file_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("config.toml"))
+    }
+
+    pub fn redirect_uri(&self) -> &str {
+        self.redirect_uri.as_deref().unwrap_or(DEFAULT_REDIRECT_URI)
+    }
+
+    pub fn token_cache_path(&self) -> Result<PathBuf> {
+        match (&self.token_cache_path, &self.profile) {
+            (Some(path), _) => Ok(path.clone()),
+            (None, Some(name)) => {
+                Ok(Self::config_dir()?.join(format!(".spotify_token_cache.{}.json", name)))
+            }
+            (None, None) => Ok(Self::config_dir()?.join(".spotify_token_cache.json")),
+        }
+    }
+
+    /// Where a running instance listens for commands: in the runtime
+    /// directory if there is one, so it's cleaned up on logout.
+    pub fn socket_path(&self) -> Result<PathBuf> {
+        let dir = match dirs::runtime_dir() {
+            Some(dir) => dir.join("crabify"),
+            None => Self::config_dir()?,
+        };
+        Ok(dir.join(format!("{}.sock", self.profile_name())))
+    }
+
+    /// Where this profile's library cache lives.
+    pub fn library_cache_dir(&self) -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("cache").join(self.profile_name()))
+    }
+
+    /// Where this profile's listens wait until they're submitted.
+    pub fn scrobble_queue_path(&self) -> Result<PathBuf> {
+        let file = format!("{}.json", self.profile_name());
+        Ok(Self::config_dir()?.join("scrobbles").join(file))
+    }
+}
+
+fn valid_profile_name(name: &str) -> bool {
+    !name.trim().is_empty() && !name.contains(['/', '\\']) && !name.contains("..")
+}
+
+fn parse_market(market: &str) -> Option<Market> {
+    if market == "from_token" {
+        return Some(Market::FromToken);
+    }
+    let code = serde_json::Value::String(market.to_uppercase());
+    serde_json::from_value::<Country>(code).ok().map(Market::Country)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> Result<AppConfig> {
+        let config: AppConfig = toml::from_str(toml)?;
+        config.validate().map(|_| config)
+    }
+
+    fn error(toml: &str) -> String {
+        parse(toml).expect_err("invalid config").to_string()
+    }
+
+    #[test]
+    fn the_template_is_valid() {
+        parse(DEFAULT_CONFIG_TOML).unwrap();
+        AppConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn names_the_key_out_of_range() {
+        assert!(error("tick_rate_ms = 10").starts_with("`tick_rate_ms` = 10:"));
+        assert!(error("volume_step = 0").starts_with("`volume_step` = 0:"));
+        assert!(error("theme = \"neon\"").contains("expected one of"));
+        assert!(error("market = \"Narnia\"").starts_with("`market`"));
+        assert!(error("[columns]\nplaylist = []").starts_with("`columns.playlist`"));
+    }
+
+    #[test]
+    fn a_remote_listener_needs_a_token() {
+        let open = "[http]\nenabled = true\nlisten = \"0.0.0.0:8787\"";
+        assert!(error(open).starts_with("`http.token`"));
+        parse(&format!("{}\ntoken = \"secret\"", open)).unwrap();
+        parse("[http]\nenabled = true\nlisten = \"127.0.0.1:8787\"").unwrap();
+        assert!(error("[http]\nlisten = \"localhost\"").starts_with("`http.listen`"));
+    }
+
+    #[test]
+    fn scrobbling_needs_a_token() {
+        assert!(error("[scrobble]\nenabled = true").starts_with("`scrobble.token`"));
+    }
+
+    #[test]
+    fn rejects_profile_names_that_arent_file_names() {
+        for name in ["../other", "a/b", "a\\\\b", " "] {
+            let toml = format!("[profiles.\"{}\"]\nclient_id = \"x\"", name);
+            assert!(error(&toml).starts_with("`profiles`"), "{:?}", name);
+        }
+        parse("[profiles.work]\nclient_id = \"x\"").unwrap();
+    }
+
+    #[test]
+    fn checks_profile_markets_and_keys() {
+        let market = "[profiles.work]\nmarket = \"zz\"";
+        assert!(error(market).starts_with("`profiles.work.market`"));
+        assert!(error("[keys]\nquit = \"g\"").starts_with("`keys`"));
+    }
+
+    #[test]
+    fn rejects_unknown_statusline_fields() {
+        let format = "[statusline]\nformat = \"{nope}\"";
+        assert!(error(format).contains("unknown field {nope}"));
     }
 }
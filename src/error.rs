@@ -1,18 +1,38 @@
+use rspotify::http::HttpError;
+use rspotify::model::IdError;
+use rspotify::ClientError;
 use thiserror::Error;
 
+pub type Result<T> = std::result::Result<T, AppError>;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Authentication failed: {0}")]
     Auth(String),
 
+    #[error("Spotify session expired")]
+    AuthExpired,
+
+    #[error("Spotify access was revoked. Log in again in your browser.")]
+    TokenRevoked,
+
     #[error("Spotify API error: {0}")]
     Api(String),
 
     #[error("No active Spotify device found. Open Spotify on a device and try again.")]
     NoActiveDevice,
 
-    #[error("Configuration error: {0}")]
-    Config(String),
+    #[error("This action requires Spotify Premium")]
+    PremiumRequired,
+
+    #[error(
+        "Rate limited by Spotify{}",
+        retry_after.map(|s| format!(", retrying in {}s", s)).unwrap_or_default()
+    )]
+    RateLimited { retry_after: Option<u64> },
+
+    #[error("Network error: {0}")]
+    Network(String),
 
     #[error(transparent)]
     Io(#[from] std::io::Error),
@@ -20,3 +40,71 @@ pub enum AppError {
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+impl AppError {
+    /// Classifies an rspotify error. Reading the body of an unsuccessful
+    /// response is async, so this can't be a plain `From` impl.
+    pub async fn from_client_error(err: ClientError) -> Self {
+        match err {
+            ClientError::Http(http) => match *http {
+                HttpError::Client(e) => AppError::Network(e.to_string()),
+                HttpError::StatusCode(response) => {
+                    let status = response.status().as_u16();
+                    let retry_after = response
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.trim().parse().ok());
+                    let body = response.text().await.unwrap_or_default();
+                    Self::from_response(status, retry_after, &body)
+                }
+            },
+            ClientError::InvalidToken => AppError::AuthExpired,
+            other => AppError::Api(other.to_string()),
+        }
+    }
+
+    fn from_response(status: u16, retry_after: Option<u64>, body: &str) -> Self {
+        let json: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+
+        // Web API errors look like `{"error": {"status", "message", "reason"}}`,
+        // while the accounts service uses `{"error": "...", "error_description": "..."}`.
+        let reason = json["error"]["reason"].as_str().unwrap_or_default();
+        let message = json["error"]["message"]
+            .as_str()
+            .or_else(|| json["error_description"].as_str())
+            .unwrap_or_default();
+        let oauth_error = json["error"].as_str().unwrap_or_default();
+
+        match status {
+            400 if oauth_error == "invalid_grant" => AppError::TokenRevoked,
+            401 => AppError::AuthExpired,
+            403 if reason == "PREMIUM_REQUIRED" || message.contains("Premium") => {
+                AppError::PremiumRequired
+            }
+            404 if reason == "NO_ACTIVE_DEVICE" => AppError::NoActiveDevice,
+            429 => AppError::RateLimited { retry_after },
+            _ if message.is_empty() => AppError::Api(format!("status code {}", status)),
+            _ => AppError::Api(format!("{} ({})", message, status)),
+        }
+    }
+
+    /// Whether the message is self-explanatory without the context of the
+    /// operation that failed.
+    pub fn is_classified(&self) -> bool {
+        matches!(
+            self,
+            AppError::AuthExpired
+                | AppError::TokenRevoked
+                | AppError::NoActiveDevice
+                | AppError::PremiumRequired
+                | AppError::RateLimited { .. }
+        )
+    }
+}
+
+impl From<IdError> for AppError {
+    fn from(err: IdError) -> Self {
+        AppError::Api(format!("Invalid Spotify ID: {}", err))
+    }
+}
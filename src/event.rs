@@ -1,41 +1,64 @@
 use anyhow::Result;
-use crossterm::event::{self, Event as CrosstermEvent, KeyEventKind};
-use std::time::Duration;
+use crossterm::event::{self, Event as CrosstermEvent, KeyEventKind, MouseEventKind};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 use crate::action::Event;
 
 pub struct EventHandler {
     rx: mpsc::UnboundedReceiver<Event>,
-    _tx: mpsc::UnboundedSender<Event>,
+    tx: mpsc::UnboundedSender<Event>,
 }
 
 impl EventHandler {
     pub fn new(tick_rate: Duration) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
-        let _tx = tx.clone();
+        let event_tx = tx.clone();
 
         tokio::spawn(async move {
+            // Ticks keep to time however busy the terminal is
+            let mut next_tick = Instant::now() + tick_rate;
             loop {
-                if event::poll(tick_rate).unwrap_or(false) {
+                let timeout = next_tick.saturating_duration_since(Instant::now());
+                if event::poll(timeout).unwrap_or(false) {
                     match event::read() {
-                        Ok(CrosstermEvent::Key(key)) => {
-                            if key.kind == KeyEventKind::Press {
-                                let _ = tx.send(Event::Key(key));
-                            }
+                        Ok(CrosstermEvent::Key(key)) if key.kind == KeyEventKind::Press => {
+                            let _ = event_tx.send(Event::Key(key));
                         }
-                        Ok(CrosstermEvent::Resize(w, h)) => {
-                            let _ = tx.send(Event::Resize(w, h));
+                        // Nothing follows the pointer, and it moves a lot
+                        Ok(CrosstermEvent::Mouse(mouse)) if mouse.kind != MouseEventKind::Moved => {
+                            let _ = event_tx.send(Event::Mouse(mouse));
+                        }
+                        Ok(CrosstermEvent::Resize(_, _)) => {
+                            let _ = event_tx.send(Event::Resize);
+                        }
+                        Ok(CrosstermEvent::FocusGained) => {
+                            let _ = event_tx.send(Event::FocusGained);
+                        }
+                        Ok(CrosstermEvent::FocusLost) => {
+                            let _ = event_tx.send(Event::FocusLost);
                         }
                         _ => {}
                     }
-                } else {
-                    let _ = tx.send(Event::Tick);
+                }
+                let now = Instant::now();
+                if now >= next_tick {
+                    let _ = event_tx.send(Event::Tick);
+                    next_tick += tick_rate;
+                    // After a stall, carry on from now rather than catching up
+                    if next_tick <= now {
+                        next_tick = now + tick_rate;
+                    }
                 }
             }
         });
 
-        Self { rx, _tx }
+        Self { rx, tx }
+    }
+
+    /// A sender for events that don't come from the terminal.
+    pub fn sender(&self) -> mpsc::UnboundedSender<Event> {
+        self.tx.clone()
     }
 
     pub async fn next(&mut self) -> Result<Event> {
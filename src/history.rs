@@ -0,0 +1,468 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use rspotify::model::{CurrentPlaybackContext, PlayableItem};
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::hooks;
+
+// Leaving a track closer to its end than this isn't a skip; crossfade and
+// polling both lose a few seconds at the end
+const SKIP_MARGIN_MS: i64 = 10_000;
+// How many entries each top list keeps
+const TOP_LEN: usize = 100;
+
+/// One track played, as a line in the history file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Play {
+    /// When it started, in seconds since the Unix epoch.
+    pub played_at: i64,
+    /// The track URI, or the name for local files.
+    pub uri: String,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub duration_ms: i64,
+    /// How long it actually played, pauses and seeks aside.
+    pub listened_ms: i64,
+    /// Whether it was left well before its end.
+    pub skipped: bool,
+    pub device: String,
+}
+
+/// Follows the track playing, to tell how long it played and whether it
+/// was skipped once the next one starts.
+#[derive(Debug, Default)]
+pub struct PlayTracker {
+    current: Option<Playing>,
+}
+
+#[derive(Debug)]
+struct Playing {
+    play: Play,
+    listened: Duration,
+    last_progress_ms: i64,
+    last_update: Instant,
+}
+
+impl PlayTracker {
+    /// Brings the tracker up to date with what's playing. Called on every
+    /// tick and poll; returns the previous play once it's over.
+    pub fn update(
+        &mut self,
+        ctx: Option<&CurrentPlaybackContext>,
+        is_playing: bool,
+        progress_ms: i64,
+    ) -> Option<Play> {
+        let key = ctx.and_then(hooks::item_key);
+        let same_item = self.current.as_ref().map(|current| &current.play.uri) == key.as_ref();
+        // Repeating a track goes from its end back to the start
+        let restarted = self.current.as_ref().is_some_and(|current| {
+            current.reached_end() && progress_ms < current.last_progress_ms / 2
+        });
+
+        if !same_item || restarted {
+            let finished = self.finish();
+            self.current = ctx.and_then(|ctx| Playing::start(ctx, progress_ms));
+            return finished;
+        }
+
+        let current = self.current.as_mut()?;
+        // Only progress made in real time counts, so seeking ahead doesn't
+        let elapsed_ms = current.last_update.elapsed().as_millis() as i64;
+        let advanced_ms = progress_ms - current.last_progress_ms;
+        if is_playing && advanced_ms > 0 {
+            current.listened += Duration::from_millis(advanced_ms.min(elapsed_ms) as u64);
+        }
+        current.last_progress_ms = progress_ms;
+        current.last_update = Instant::now();
+        None
+    }
+
+    /// Ends the current play, e.g. on exit. Tracks that never played, like
+    /// ones passed over while paused, aren't plays.
+    pub fn finish(&mut self) -> Option<Play> {
+        let current = self.current.take()?;
+        let skipped = !current.reached_end();
+        let mut play = current.play;
+        play.listened_ms = current.listened.as_millis() as i64;
+        play.skipped = skipped;
+        (play.listened_ms > 0).then_some(play)
+    }
+}
+
+impl Playing {
+    /// Only music is recorded.
+    fn start(ctx: &CurrentPlaybackContext, progress_ms: i64) -> Option<Self> {
+        let Some(PlayableItem::Track(track)) = &ctx.item else {
+            return None;
+        };
+        let play = Play {
+            played_at: chrono::Utc::now().timestamp(),
+            uri: hooks::item_key(ctx)?,
+            title: track.name.clone(),
+            artists: track.artists.iter().map(|a| a.name.clone()).collect(),
+            album: track.album.name.clone(),
+            duration_ms: track.duration.num_milliseconds(),
+            listened_ms: 0,
+            skipped: false,
+            device: ctx.device.name.clone(),
+        };
+        Some(Self {
+            play,
+            listened: Duration::ZERO,
+            last_progress_ms: progress_ms,
+            last_update: Instant::now(),
+        })
+    }
+
+    fn reached_end(&self) -> bool {
+        self.last_progress_ms + SKIP_MARGIN_MS >= self.play.duration_ms
+    }
+}
+
+/// The profile's plays, one JSON object per line.
+pub struct ListeningHistory {
+    path: PathBuf,
+}
+
+impl ListeningHistory {
+    /// Opens the history for `profile`, by name.
+    pub fn open(profile: &str) -> Result<Self> {
+        let dir = AppConfig::config_dir()?.join("history");
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            path: dir.join(format!("{}.jsonl", profile)),
+        })
+    }
+
+    pub fn record(&self, play: &Play) -> Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(play)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Every play recorded, oldest first. Lines that don't parse, like one
+    /// cut short by a crash, are skipped.
+    pub fn load(&self) -> Vec<Play> {
+        let contents = std::fs::read_to_string(&self.path).unwrap_or_default();
+        contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+    }
+}
+
+/// How far back the stats screen looks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Period {
+    #[default]
+    Week,
+    Month,
+    AllTime,
+}
+
+impl Period {
+    pub fn all() -> &'static [Period] {
+        &[Period::Week, Period::Month, Period::AllTime]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Period::Week => "Past 7 days",
+            Period::Month => "Past 30 days",
+            Period::AllTime => "All time",
+        }
+    }
+
+    pub fn next(self) -> Period {
+        match self {
+            Period::Week => Period::Month,
+            Period::Month => Period::AllTime,
+            Period::AllTime => Period::Week,
+        }
+    }
+
+    fn days(self) -> Option<i64> {
+        match self {
+            Period::Week => Some(7),
+            Period::Month => Some(30),
+            Period::AllTime => None,
+        }
+    }
+}
+
+/// A track, artist or album in a top list.
+#[derive(Debug, Clone, Default)]
+pub struct Ranked {
+    /// The track URI, for tracks; otherwise the name.
+    pub key: String,
+    pub name: String,
+    /// The artists, for tracks and albums.
+    pub detail: String,
+    pub plays: usize,
+    pub skips: usize,
+    pub listened_ms: i64,
+}
+
+impl Ranked {
+    fn add(&mut self, play: &Play) {
+        self.plays += 1;
+        self.skips += play.skipped as usize;
+        self.listened_ms += play.listened_ms;
+    }
+}
+
+/// What the stats screen shows for one period.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub period: Period,
+    pub plays: usize,
+    pub skips: usize,
+    pub listened_ms: i64,
+    /// Most listened to first, by time.
+    pub tracks: Vec<Ranked>,
+    pub artists: Vec<Ranked>,
+    pub albums: Vec<Ranked>,
+}
+
+impl Stats {
+    /// Sums up the plays in `period`, as of `now` in seconds since the
+    /// Unix epoch.
+    pub fn new(plays: &[Play], period: Period, now: i64) -> Self {
+        let since = period.days().map_or(i64::MIN, |days| now - days * 24 * 60 * 60);
+        let mut stats = Stats {
+            period,
+            ..Stats::default()
+        };
+        let mut tracks: HashMap<&str, Ranked> = HashMap::new();
+        let mut artists: HashMap<&str, Ranked> = HashMap::new();
+        let mut albums: HashMap<(&str, &str), Ranked> = HashMap::new();
+
+        for play in plays.iter().filter(|play| play.played_at >= since) {
+            stats.plays += 1;
+            stats.skips += play.skipped as usize;
+            stats.listened_ms += play.listened_ms;
+
+            let track = tracks.entry(&play.uri).or_insert_with(|| Ranked {
+                key: play.uri.clone(),
+                name: play.title.clone(),
+                detail: play.artists.join(", "),
+                ..Ranked::default()
+            });
+            track.add(play);
+            for artist in &play.artists {
+                let ranked = artists.entry(artist).or_insert_with(|| Ranked {
+                    key: artist.clone(),
+                    name: artist.clone(),
+                    ..Ranked::default()
+                });
+                ranked.add(play);
+            }
+            // Albums of the same name by different artists are different albums
+            let album_artist = play.artists.first().map(String::as_str).unwrap_or_default();
+            let album = albums.entry((&play.album, album_artist)).or_insert_with(|| Ranked {
+                key: play.album.clone(),
+                name: play.album.clone(),
+                detail: album_artist.to_string(),
+                ..Ranked::default()
+            });
+            album.add(play);
+        }
+
+        stats.tracks = top(tracks.into_values());
+        stats.artists = top(artists.into_values());
+        stats.albums = top(albums.into_values());
+        stats
+    }
+}
+
+fn top(ranked: impl Iterator<Item = Ranked>) -> Vec<Ranked> {
+    let mut ranked: Vec<Ranked> = ranked.collect();
+    ranked.sort_by(|a, b| b.listened_ms.cmp(&a.listened_ms).then_with(|| a.name.cmp(&b.name)));
+    ranked.truncate(TOP_LEN);
+    ranked
+}
+
+/// A skip rate as a percentage.
+pub fn skip_rate(skips: usize, plays: usize) -> String {
+    match plays {
+        0 => "–".to_string(),
+        _ => format!("{}%", (skips * 100 + plays / 2) / plays),
+    }
+}
+
+/// A listening time as hours and minutes.
+pub fn format_listened(ms: i64) -> String {
+    let minutes = ms / 60_000;
+    match minutes / 60 {
+        0 => format!("{}m", minutes),
+        hours => format!("{}h {:02}m", hours, minutes % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn playback(id: &str, duration_ms: i64) -> CurrentPlaybackContext {
+        serde_json::from_value(json!({
+            "device": {
+                "id": "device",
+                "is_active": true,
+                "is_private_session": false,
+                "is_restricted": false,
+                "name": "Desk",
+                "type": "Computer",
+                "volume_percent": 50,
+            },
+            "repeat_state": "off",
+            "shuffle_state": false,
+            "context": null,
+            "timestamp": 0,
+            "progress_ms": 0,
+            "is_playing": true,
+            "currently_playing_type": "track",
+            "actions": { "disallows": {} },
+            "item": {
+                "type": "track",
+                "id": id,
+                "uri": format!("spotify:track:{}", id),
+                "name": "Song",
+                "duration_ms": duration_ms,
+                "artists": [{ "name": "Artist", "external_urls": {} }],
+                "album": {
+                    "name": "Album",
+                    "artists": [],
+                    "images": [],
+                    "external_urls": {},
+                    "album_type": "album",
+                },
+                "available_markets": [],
+                "disc_number": 1,
+                "explicit": false,
+                "external_ids": {},
+                "external_urls": {},
+                "is_local": false,
+                "popularity": 0,
+                "track_number": 1,
+                "href": null,
+                "preview_url": null,
+            },
+        }))
+        .expect("valid playback")
+    }
+
+    #[test]
+    fn leaving_early_is_a_skip() {
+        let (a, b) = (playback("a", 60_000), playback("b", 60_000));
+        let mut tracker = PlayTracker::default();
+        assert!(tracker.update(Some(&a), true, 0).is_none());
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(tracker.update(Some(&a), true, 100).is_none());
+
+        let play = tracker.update(Some(&b), true, 0).expect("a play");
+        assert_eq!(play.uri, "spotify:track:a");
+        assert_eq!((play.title.as_str(), play.device.as_str()), ("Song", "Desk"));
+        assert_eq!(play.listened_ms, 100);
+        assert!(play.skipped);
+    }
+
+    #[test]
+    fn seeking_ahead_doesnt_count_as_listening() {
+        let a = playback("a", 60_000);
+        let mut tracker = PlayTracker::default();
+        tracker.update(Some(&a), true, 0);
+        std::thread::sleep(Duration::from_millis(50));
+        tracker.update(Some(&a), true, 55_000);
+
+        // Close enough to the end not to be a skip
+        let play = tracker.finish().expect("a play");
+        assert!((50..1000).contains(&play.listened_ms), "{}", play.listened_ms);
+        assert!(!play.skipped);
+    }
+
+    #[test]
+    fn repeating_a_track_is_another_play() {
+        let a = playback("a", 60_000);
+        let mut tracker = PlayTracker::default();
+        tracker.update(Some(&a), true, 59_900);
+        std::thread::sleep(Duration::from_millis(50));
+        tracker.update(Some(&a), true, 59_950);
+        let play = tracker.update(Some(&a), true, 0).expect("the first play");
+        assert!(!play.skipped);
+    }
+
+    #[test]
+    fn tracks_passed_over_while_paused_arent_plays() {
+        let (a, b) = (playback("a", 60_000), playback("b", 60_000));
+        let mut tracker = PlayTracker::default();
+        tracker.update(Some(&a), false, 0);
+        assert!(tracker.update(Some(&b), false, 0).is_none());
+        assert!(tracker.update(None, false, 0).is_none());
+    }
+
+    fn play(played_at: i64, title: &str, artists: &[&str], listened_ms: i64) -> Play {
+        Play {
+            played_at,
+            uri: format!("spotify:track:{}", title),
+            title: title.to_string(),
+            artists: artists.iter().map(|a| a.to_string()).collect(),
+            album: "Greatest Hits".to_string(),
+            duration_ms: 200_000,
+            listened_ms,
+            skipped: listened_ms < 100_000,
+            device: "Desk".to_string(),
+        }
+    }
+
+    #[test]
+    fn sums_up_the_period() {
+        let day = 24 * 60 * 60;
+        let now = 100 * day;
+        let plays = [
+            play(now - 40 * day, "old", &["A"], 200_000),
+            play(now - 10 * day, "one", &["A"], 50_000),
+            play(now - day, "two", &["B", "A"], 200_000),
+            play(now, "one", &["A"], 200_000),
+        ];
+
+        let week = Stats::new(&plays, Period::Week, now);
+        assert_eq!((week.plays, week.skips, week.listened_ms), (2, 0, 400_000));
+        let month = Stats::new(&plays, Period::Month, now);
+        assert_eq!((month.plays, month.skips, month.listened_ms), (3, 1, 450_000));
+        let all = Stats::new(&plays, Period::AllTime, now);
+        assert_eq!(all.plays, 4);
+
+        // Most listened first; ties by name
+        let tracks: Vec<_> = month.tracks.iter().map(|t| (t.name.as_str(), t.plays)).collect();
+        assert_eq!(tracks, [("one", 2), ("two", 1)]);
+        assert_eq!(month.tracks[1].detail, "B, A");
+        let artists: Vec<_> = month.artists.iter().map(|a| (a.name.as_str(), a.plays)).collect();
+        assert_eq!(artists, [("A", 3), ("B", 1)]);
+    }
+
+    #[test]
+    fn albums_are_told_apart_by_artist() {
+        let plays = [play(0, "one", &["A"], 1000), play(0, "two", &["B"], 2000)];
+        let stats = Stats::new(&plays, Period::AllTime, 0);
+        let albums: Vec<_> = stats.albums.iter().map(|a| a.detail.as_str()).collect();
+        assert_eq!(albums, ["B", "A"]);
+    }
+
+    #[test]
+    fn formats_rates_and_times() {
+        assert_eq!(skip_rate(0, 0), "–");
+        assert_eq!(skip_rate(1, 3), "33%");
+        assert_eq!(skip_rate(2, 3), "67%");
+        assert_eq!(format_listened(59 * 60_000), "59m");
+        assert_eq!(format_listened(125 * 60_000), "2h 05m");
+    }
+}
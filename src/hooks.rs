@@ -0,0 +1,117 @@
+use std::process::Stdio;
+
+use rspotify::model::{CurrentPlaybackContext, PlayableItem};
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::statusline;
+
+/// A playback event that can run a shell command from `[hooks]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    TrackChanged,
+    Paused,
+    Resumed,
+    TrackLiked,
+    DeviceChanged,
+}
+
+impl Hook {
+    /// The key in `[hooks]`, also passed to the command as `CRABIFY_EVENT`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::TrackChanged => "track_changed",
+            Hook::Paused => "paused",
+            Hook::Resumed => "resumed",
+            Hook::TrackLiked => "track_liked",
+            Hook::DeviceChanged => "device_changed",
+        }
+    }
+
+    /// The events between two successive polls of the playback state.
+    pub fn transitions(
+        old: Option<&CurrentPlaybackContext>,
+        new: Option<&CurrentPlaybackContext>,
+    ) -> Vec<Hook> {
+        let mut hooks = Vec::new();
+        let new_item = new.and_then(item_key);
+        if new_item.is_some() && new_item != old.and_then(item_key) {
+            hooks.push(Hook::TrackChanged);
+        }
+        let was_playing = old.is_some_and(|ctx| ctx.is_playing);
+        match (was_playing, new.is_some_and(|ctx| ctx.is_playing)) {
+            (true, false) => hooks.push(Hook::Paused),
+            (false, true) => hooks.push(Hook::Resumed),
+            _ => {}
+        }
+        if let (Some(old), Some(new)) = (old, new) {
+            if old.device.id != new.device.id {
+                hooks.push(Hook::DeviceChanged);
+            }
+        }
+        hooks
+    }
+}
+
+/// Tells items apart; local files have no ID, only a name.
+pub fn item_key(ctx: &CurrentPlaybackContext) -> Option<String> {
+    ctx.item.as_ref().map(|item| match item {
+        PlayableItem::Track(track) => match &track.id {
+            Some(id) => id.to_string(),
+            None => track.name.clone(),
+        },
+        PlayableItem::Episode(episode) => episode.id.to_string(),
+    })
+}
+
+/// Starts `command` with the shell, without waiting for it. The event and
+/// track are in `CRABIFY_*` variables and `payload` is on stdin as JSON.
+/// Output is discarded so it can't draw over the TUI.
+pub fn run(command: &str, hook: Hook, payload: &Value) -> std::io::Result<()> {
+    let field = |name: &str| statusline::field_value(name, payload);
+    let item = &payload["item"];
+    let number = |value: &Value| value.as_i64().map(|n| n.to_string()).unwrap_or_default();
+
+    let mut child = shell(command)
+        .env("CRABIFY_EVENT", hook.name())
+        .env("CRABIFY_TITLE", field("title"))
+        .env("CRABIFY_ARTIST", field("artist"))
+        .env("CRABIFY_ALBUM", field("album"))
+        .env("CRABIFY_URI", item["uri"].as_str().unwrap_or_default())
+        .env("CRABIFY_DURATION_MS", number(&item["duration_ms"]))
+        .env("CRABIFY_DEVICE", field("device"))
+        .env("CRABIFY_IS_PLAYING", (payload["is_playing"] == true).to_string())
+        .env("CRABIFY_LIKED", (payload["liked"] == true).to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let mut input = payload.to_string();
+    input.push('\n');
+    let mut stdin = child.stdin.take();
+    tokio::spawn(async move {
+        // A command that ignores stdin may exit before reading it
+        if let Some(stdin) = &mut stdin {
+            let _ = stdin.write_all(input.as_bytes()).await;
+        }
+        drop(stdin);
+        let _ = child.wait().await;
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
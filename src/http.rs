@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tokio::runtime::Handle;
+use tokio::sync::{mpsc, watch};
+
+use crate::action::{Event, Remote};
+use crate::cli;
+use crate::config::{HttpConfig, PartyConfig};
+use crate::error::AppError;
+use crate::spotify::SpotifyClient;
+
+const PAGE: &str = include_str!("remote.html");
+// Bodies are a few fields of JSON
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+// The window `suggestions_per_hour` counts over
+const SUGGESTION_WINDOW: Duration = Duration::from_secs(60 * 60);
+// Requests answered at once; more wait their turn
+const WORKERS: usize = 4;
+
+/// The web remote: a page for phones and a JSON API under `/api/`.
+pub struct RemoteServer {
+    server: Server,
+    token: Option<String>,
+    party: PartyConfig,
+}
+
+/// What each request needs, shared by the threads answering them.
+#[derive(Clone)]
+struct Handler {
+    token: Option<String>,
+    party: PartyConfig,
+    events: mpsc::UnboundedSender<Event>,
+    status: watch::Receiver<Value>,
+    suggestions: watch::Receiver<Value>,
+    /// The current account's client, replaced when the account switches.
+    client: watch::Receiver<SpotifyClient>,
+    /// When each guest suggested their recent tracks.
+    suggested: Arc<Mutex<HashMap<String, Vec<Instant>>>>,
+}
+
+/// A request that can't be answered, as an HTTP status and message.
+struct Failure(u16, String);
+
+impl From<AppError> for Failure {
+    fn from(error: AppError) -> Self {
+        let status = match error {
+            AppError::NoActiveDevice => 409,
+            AppError::RateLimited { .. } => 429,
+            _ => 502,
+        };
+        Failure(status, error.to_string())
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> Failure {
+    Failure(400, message.into())
+}
+
+impl RemoteServer {
+    /// Listens on the configured address, so a port in use is reported
+    /// before the TUI takes over the terminal.
+    pub fn bind(config: &HttpConfig, party: &PartyConfig) -> Result<Self> {
+        let server = Server::http(&config.listen).map_err(|e| {
+            anyhow::anyhow!("Could not listen on {} for the web remote: {}", config.listen, e)
+        })?;
+        let token = Some(config.token.trim().to_string()).filter(|token| !token.is_empty());
+        Ok(Self {
+            server,
+            token,
+            party: party.clone(),
+        })
+    }
+
+    /// Answers requests on a few threads of its own until the process exits.
+    /// Playback controls and party suggestions go to the main loop through
+    /// `events`; searches and the queue go straight to Spotify with
+    /// the latest `client`. `suggestions` is the party queue as JSON.
+    pub fn serve(
+        self,
+        events: mpsc::UnboundedSender<Event>,
+        status: watch::Receiver<Value>,
+        suggestions: watch::Receiver<Value>,
+        client: watch::Receiver<SpotifyClient>,
+    ) {
+        let handler = Handler {
+            token: self.token,
+            party: self.party,
+            events,
+            status,
+            suggestions,
+            client,
+            suggested: Arc::default(),
+        };
+        let runtime = Handle::current();
+        let server = Arc::new(self.server);
+        // A slow search shouldn't hold up everyone else's buttons
+        for _ in 0..WORKERS {
+            let server = server.clone();
+            let handler = handler.clone();
+            let runtime = runtime.clone();
+            std::thread::spawn(move || {
+                while let Ok(mut request) = server.recv() {
+                    let response = runtime.block_on(handler.respond(&mut request));
+                    let _ = request.respond(response);
+                }
+            });
+        }
+    }
+}
+
+impl Handler {
+    async fn respond(&self, request: &mut Request) -> Response<Cursor<Vec<u8>>> {
+        let base = url::Url::parse("http://localhost").expect("valid URL");
+        let url = match base.join(request.url()) {
+            Ok(url) => url,
+            Err(e) => return error_response(bad_request(e.to_string())),
+        };
+        if !self.authorized(request, &url) {
+            return error_response(Failure(401, "missing or wrong token".to_string()));
+        }
+        if *request.method() == Method::Get && url.path() == "/" {
+            let html = content_type("text/html; charset=utf-8");
+            return Response::from_string(PAGE).with_header(html);
+        }
+        match self.api(request, &url).await {
+            Ok(body) => json_response(200, &body),
+            Err(failure) => error_response(failure),
+        }
+    }
+
+    fn authorized(&self, request: &Request, url: &url::Url) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let bearer = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Authorization"))
+            .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+            .map(str::to_string);
+        let query = url.query_pairs().find(|(key, _)| key == "token").map(|(_, v)| v.into_owned());
+        bearer.or(query).is_some_and(|given| same_token(&given, token))
+    }
+
+    async fn api(&self, request: &mut Request, url: &url::Url) -> Result<Value, Failure> {
+        let method = request.method().clone();
+        let guest = request.remote_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+        let Some(path) = url.path().strip_prefix("/api/") else {
+            return Err(Failure(404, "not found".to_string()));
+        };
+        let body = read_body(request)?;
+        let ok = json!({ "ok": true });
+
+        match (method, path) {
+            (Method::Get, "status") => Ok(self.status.borrow().clone()),
+            (Method::Post, "play") => {
+                match body["uri"].as_str() {
+                    Some(uri) => {
+                        self.check_not_party()?;
+                        self.client().play_track(uri).await?;
+                    }
+                    None => self.send(Remote::Play)?,
+                }
+                Ok(ok)
+            }
+            (Method::Post, "pause") => self.send(Remote::Pause).map(|_| ok),
+            (Method::Post, "toggle") => self.send(Remote::PlayPause).map(|_| ok),
+            (Method::Post, "next") => self.send(Remote::Next).map(|_| ok),
+            (Method::Post, "prev") => self.send(Remote::Previous).map(|_| ok),
+            (Method::Post, "volume") => {
+                let remote = match (body["volume"].as_u64(), body["by"].as_i64()) {
+                    (Some(volume), _) if volume <= 100 => Remote::SetVolume(volume as u8),
+                    (None, Some(by)) => Remote::ChangeVolume(by.clamp(-100, 100) as i32),
+                    _ => return Err(bad_request("expected {\"volume\": 0-100} or {\"by\": ±N}")),
+                };
+                self.send(remote).map(|_| ok)
+            }
+            (Method::Get, "search") => {
+                let query = url
+                    .query_pairs()
+                    .find(|(key, _)| key == "q")
+                    .map(|(_, query)| query.trim().to_string())
+                    .filter(|query| !query.is_empty())
+                    .ok_or_else(|| bad_request("expected ?q=<query>"))?;
+                let tracks = self.client().search_tracks(&query).await?;
+                let tracks: Vec<Value> = tracks.iter().map(cli::track_json).collect();
+                Ok(json!({ "tracks": tracks }))
+            }
+            (Method::Get, "queue") => {
+                let (current, queue) = self.client().fetch_queue().await?;
+                let queue: Vec<Value> = queue.iter().map(cli::item_json).collect();
+                Ok(json!({ "current": current.as_ref().map(cli::item_json), "queue": queue }))
+            }
+            (Method::Post, "queue") => {
+                self.check_not_party()?;
+                let uri = body["uri"].as_str().ok_or_else(|| bad_request("expected a \"uri\""))?;
+                self.client().add_to_queue(uri).await?;
+                Ok(ok)
+            }
+            (Method::Get, "party") => {
+                let mut suggestions = self.suggestions.borrow().clone();
+                // Guests only learn what they voted for themselves
+                let suggestion_objects = suggestions.as_array_mut().into_iter().flatten();
+                for suggestion in suggestion_objects.filter_map(Value::as_object_mut) {
+                    let voters = suggestion.remove("voters").unwrap_or_default();
+                    let voters = voters.as_array().map(Vec::as_slice).unwrap_or_default();
+                    let voted = voters.iter().any(|voter| *voter == guest.as_str());
+                    suggestion.insert("voted".to_string(), voted.into());
+                }
+                Ok(json!({ "enabled": self.party.enabled, "suggestions": suggestions }))
+            }
+            (Method::Post, "party") => {
+                self.check_party()?;
+                let uri = body["uri"].as_str().ok_or_else(|| bad_request("expected a \"uri\""))?;
+                let reserved = self.reserve_suggestion(&guest)?;
+                let suggested = match self.client().fetch_track(uri).await {
+                    Ok(track) => self.send(Remote::Suggest {
+                        guest: guest.clone(),
+                        track: Box::new(track),
+                    }),
+                    Err(e) => Err(e.into()),
+                };
+                if suggested.is_err() {
+                    self.release_suggestion(&guest, reserved);
+                }
+                suggested.map(|_| ok)
+            }
+            (Method::Post, "party/vote") => {
+                self.check_party()?;
+                let id = body["id"].as_u64().ok_or_else(|| bad_request("expected an \"id\""))?;
+                self.send(Remote::Vote { guest, id }).map(|_| ok)
+            }
+            _ => Err(Failure(404, "not found".to_string())),
+        }
+    }
+
+    fn client(&self) -> SpotifyClient {
+        self.client.borrow().clone()
+    }
+
+    fn check_party(&self) -> Result<(), Failure> {
+        match self.party.enabled {
+            true => Ok(()),
+            false => Err(Failure(403, "the party queue is turned off".to_string())),
+        }
+    }
+
+    /// Guests go through the party queue rather than jumping it.
+    fn check_not_party(&self) -> Result<(), Failure> {
+        match self.party.enabled {
+            true => Err(Failure(403, "suggest tracks to the party queue instead".to_string())),
+            false => Ok(()),
+        }
+    }
+
+    /// Takes one of the suggestions `guest` has left this hour, under the
+    /// same lock as the check so parallel requests can't both get the last.
+    fn reserve_suggestion(&self, guest: &str) -> Result<Instant, Failure> {
+        let mut suggested = self.suggested.lock().expect("not poisoned");
+        let times = suggested.entry(guest.to_string()).or_default();
+        times.retain(|at| at.elapsed() < SUGGESTION_WINDOW);
+        if times.len() >= self.party.suggestions_per_hour as usize {
+            let wait = SUGGESTION_WINDOW.saturating_sub(times[0].elapsed());
+            let message = format!(
+                "you've suggested {} tracks this hour; try again in {} minutes",
+                times.len(),
+                wait.as_secs().div_ceil(60)
+            );
+            return Err(Failure(429, message));
+        }
+        let now = Instant::now();
+        times.push(now);
+        Ok(now)
+    }
+
+    /// Gives back a suggestion that didn't go through.
+    fn release_suggestion(&self, guest: &str, reserved: Instant) {
+        let mut suggested = self.suggested.lock().expect("not poisoned");
+        if let Some(times) = suggested.get_mut(guest) {
+            times.retain(|at| *at != reserved);
+        }
+    }
+
+    fn send(&self, remote: Remote) -> Result<(), Failure> {
+        self.events
+            .send(Event::Remote(remote))
+            .map_err(|_| Failure(503, "crabify is shutting down".to_string()))
+    }
+}
+
+/// The request body as JSON; `null` if there isn't one.
+/// Compares digests of the tokens without stopping at the first difference,
+/// so response times don't give away how much of a guess was right.
+fn same_token(given: &str, token: &str) -> bool {
+    let (given, token) = (Sha256::digest(given), Sha256::digest(token));
+    given.iter().zip(token.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn read_body(request: &mut Request) -> Result<Value, Failure> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|e| bad_request(e.to_string()))?;
+    if body.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_str(&body).map_err(|e| bad_request(e.to_string()))
+}
+
+fn json_response(status: u16, body: &Value) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(content_type("application/json"))
+}
+
+fn error_response(Failure(status, message): Failure) -> Response<Cursor<Vec<u8>>> {
+    json_response(status, &json!({ "ok": false, "error": message }))
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).expect("valid header")
+}
@@ -0,0 +1,508 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Every command a key can be bound to. The config file refers to them by
+/// their snake_case name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    Quit,
+    Help,
+    NextScreen,
+    PrevScreen,
+    MoveDown,
+    MoveUp,
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    GoToTop,
+    GoToBottom,
+    ViewTop,
+    ViewMiddle,
+    ViewBottom,
+    JumpToPlaying,
+    Select,
+    Back,
+    Search,
+    PlayPause,
+    NextTrack,
+    PreviousTrack,
+    VolumeUp,
+    VolumeDown,
+    ToggleLike,
+    ToggleLikeNowPlaying,
+    Devices,
+    Accounts,
+    PartyQueue,
+    Remove,
+    Stats,
+    Palette,
+}
+
+// Default bindings and help text, in the order the help overlay lists them
+const DEFAULT_BINDINGS: &[(Command, &str, &[&str])] = &[
+    (Command::Quit, "Quit", &["q"]),
+    (Command::NextScreen, "Switch screen / panel", &["tab"]),
+    (Command::PrevScreen, "Previous screen", &["shift+tab"]),
+    (Command::MoveDown, "Move down", &["j", "down"]),
+    (Command::MoveUp, "Move up", &["k", "up"]),
+    (Command::HalfPageDown, "Half a page down", &["ctrl+d"]),
+    (Command::HalfPageUp, "Half a page up", &["ctrl+u"]),
+    (Command::PageDown, "Page down", &["ctrl+f", "pagedown"]),
+    (Command::PageUp, "Page up", &["ctrl+b", "pageup"]),
+    (Command::GoToTop, "Go to first row", &["gg", "home"]),
+    (Command::GoToBottom, "Go to last row", &["G", "end"]),
+    (Command::ViewTop, "Go to top of view", &["H"]),
+    (Command::ViewMiddle, "Go to middle of view", &["M"]),
+    (Command::ViewBottom, "Go to bottom of view", &["L"]),
+    (Command::JumpToPlaying, "Jump to playing track", &["gp"]),
+    (Command::Select, "Select / Play", &["enter"]),
+    (Command::Back, "Close popup", &["esc"]),
+    (Command::Search, "Start search", &["/"]),
+    (Command::PlayPause, "Play / Pause", &["space"]),
+    (Command::NextTrack, "Next track", &["n"]),
+    (Command::PreviousTrack, "Previous track", &["p"]),
+    (Command::VolumeUp, "Volume up", &["+", "="]),
+    (Command::VolumeDown, "Volume down", &["-"]),
+    (Command::ToggleLike, "Toggle like (selected)", &["s"]),
+    (Command::ToggleLikeNowPlaying, "Toggle like (now playing)", &["l"]),
+    (Command::Devices, "Select device", &["d"]),
+    (Command::Accounts, "Switch account", &["a"]),
+    (Command::PartyQueue, "Party queue", &["P"]),
+    (Command::Remove, "Remove from party queue", &["x", "delete"]),
+    (Command::Stats, "Stats / next stats period", &["t"]),
+    (Command::Palette, "Command palette", &[":"]),
+    (Command::Help, "Toggle help", &["?"]),
+];
+
+impl Command {
+    pub fn description(self) -> &'static str {
+        DEFAULT_BINDINGS
+            .iter()
+            .find(|(command, _, _)| *command == self)
+            .map(|(_, description, _)| *description)
+            .unwrap_or_default()
+    }
+
+    /// Whether this moves the selection in a list, taking a count like `5j`.
+    pub fn is_motion(self) -> bool {
+        matches!(
+            self,
+            Command::MoveDown
+                | Command::MoveUp
+                | Command::HalfPageDown
+                | Command::HalfPageUp
+                | Command::PageDown
+                | Command::PageUp
+                | Command::GoToTop
+                | Command::GoToBottom
+                | Command::ViewTop
+                | Command::ViewMiddle
+                | Command::ViewBottom
+        )
+    }
+}
+
+/// A single key press with its modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already part of the character (and of BackTab)
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    /// The digit this key types, if it's a plain digit.
+    pub fn digit(&self) -> Option<usize> {
+        match self.code {
+            KeyCode::Char(c) if self.modifiers.is_empty() => c.to_digit(10).map(|d| d as usize),
+            _ => None,
+        }
+    }
+
+    fn is_plain_char(&self) -> bool {
+        matches!(self.code, KeyCode::Char(c) if c != ' ') && self.modifiers.is_empty()
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Key::new(event.code, event.modifiers)
+    }
+}
+
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("space", KeyCode::Char(' ')),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, code)| *code == self.code) {
+            return write!(f, "{}", name);
+        }
+        match self.code {
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::BackTab => write!(f, "shift+tab"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// One or more keys pressed in order, e.g. `gg` or `ctrl+w j`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(Vec<Key>);
+
+impl std::str::FromStr for KeySequence {
+    type Err = String;
+
+    /// Parses whitespace-separated keys. A key is a name (`enter`, `space`,
+    /// `f5`, ...) or a character, optionally prefixed with `ctrl+`, `alt+` or
+    /// `shift+`. A run of plain characters like `gg` is a chord.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut keys = Vec::new();
+        for token in spec.split_whitespace() {
+            keys.extend(parse_token(token)?);
+        }
+        if keys.is_empty() {
+            return Err("empty key binding".to_string());
+        }
+        Ok(KeySequence(keys))
+    }
+}
+
+fn parse_token(token: &str) -> Result<Vec<Key>, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = token;
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        let (modifier, len) = if lower.starts_with("ctrl+") && rest.len() > 5 {
+            (KeyModifiers::CONTROL, 5)
+        } else if lower.starts_with("alt+") && rest.len() > 4 {
+            (KeyModifiers::ALT, 4)
+        } else if lower.starts_with("shift+") && rest.len() > 6 {
+            (KeyModifiers::SHIFT, 6)
+        } else {
+            break;
+        };
+        modifiers |= modifier;
+        rest = &rest[len..];
+    }
+
+    let lower = rest.to_ascii_lowercase();
+    if let Some((_, code)) = NAMED_KEYS.iter().find(|(name, _)| *name == lower) {
+        if *code == KeyCode::Tab && modifiers.contains(KeyModifiers::SHIFT) {
+            return Ok(vec![Key::new(KeyCode::BackTab, modifiers)]);
+        }
+        return Ok(vec![Key::new(*code, modifiers)]);
+    }
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        if (1..=12).contains(&n) {
+            return Ok(vec![Key::new(KeyCode::F(n), modifiers)]);
+        }
+    }
+
+    let mut chars = rest.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => {
+            let c = if modifiers.contains(KeyModifiers::SHIFT) {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            };
+            Ok(vec![Key::new(KeyCode::Char(c), modifiers)])
+        }
+        (Some(_), Some(_)) if modifiers.is_empty() => Ok(rest
+            .chars()
+            .map(|c| Key::new(KeyCode::Char(c), KeyModifiers::NONE))
+            .collect()),
+        _ => Err(format!("unknown key `{}`", token)),
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Runs of plain characters read as chords, like `gg`
+        for (i, key) in self.0.iter().enumerate() {
+            let glue = i > 0 && !(key.is_plain_char() && self.0[i - 1].is_plain_char());
+            if glue {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", key)?;
+        }
+        Ok(())
+    }
+}
+
+/// The keys bound to a command in the config file: one spec or a list.
+#[derive(Debug, Clone, Default)]
+pub struct KeyBindings(pub Vec<KeySequence>);
+
+impl<'de> Deserialize<'de> for KeyBindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let specs = match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(spec) => vec![spec],
+            OneOrMany::Many(specs) => specs,
+        };
+        specs
+            .into_iter()
+            .map(|spec| spec.parse().map_err(serde::de::Error::custom))
+            .collect::<Result<_, _>>()
+            .map(KeyBindings)
+    }
+}
+
+impl Serialize for KeyBindings {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(ToString::to_string))
+    }
+}
+
+/// What the keys typed so far amount to.
+pub enum KeyMatch {
+    Command(Command),
+    /// The start of a longer binding; wait for more keys.
+    Pending,
+    None,
+}
+
+/// The active key bindings: the defaults with the user's overrides applied.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeySequence, Command>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new()).expect("default key bindings are valid")
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap. Rebinding a command replaces all of its default
+    /// keys, and a key the user binds is taken away from any default command.
+    pub fn new(overrides: &BTreeMap<Command, KeyBindings>) -> Result<Self, String> {
+        let user_keys: Vec<&KeySequence> = overrides.values().flat_map(|b| &b.0).collect();
+
+        let mut bindings = HashMap::new();
+        for (command, _, keys) in DEFAULT_BINDINGS {
+            if overrides.contains_key(command) {
+                continue;
+            }
+            for spec in *keys {
+                let keys: KeySequence = spec.parse().expect("default key binding parses");
+                if !user_keys.contains(&&keys) {
+                    bindings.insert(keys, *command);
+                }
+            }
+        }
+
+        for (command, keys) in overrides {
+            for keys in &keys.0 {
+                if let Some(other) = bindings.insert(keys.clone(), *command) {
+                    if other != *command {
+                        return Err(format!(
+                            "`{}` is bound to both `{}` and `{}`",
+                            keys,
+                            command_name(other),
+                            command_name(*command)
+                        ));
+                    }
+                }
+            }
+        }
+
+        // A binding that starts another one could never be reached
+        for (keys, command) in &bindings {
+            for (other_keys, other) in &bindings {
+                if other_keys.0.len() > keys.0.len() && other_keys.0.starts_with(&keys.0) {
+                    return Err(format!(
+                        "`{}` (`{}`) is a prefix of `{}` (`{}`)",
+                        keys,
+                        command_name(*command),
+                        other_keys,
+                        command_name(*other)
+                    ));
+                }
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+
+    /// Looks up the keys typed so far.
+    pub fn lookup(&self, keys: &[Key]) -> KeyMatch {
+        if let Some(command) = self.bindings.get(&KeySequence(keys.to_vec())) {
+            return KeyMatch::Command(*command);
+        }
+        if self.bindings.keys().any(|k| k.0.starts_with(keys)) {
+            return KeyMatch::Pending;
+        }
+        KeyMatch::None
+    }
+
+    /// The keys bound to `command`, shortest first.
+    pub fn keys_for(&self, command: Command) -> Vec<String> {
+        let mut keys: Vec<&KeySequence> = self
+            .bindings
+            .iter()
+            .filter(|(_, c)| **c == command)
+            .map(|(keys, _)| keys)
+            .collect();
+        keys.sort_by_key(|keys| (keys.0.len(), keys.to_string()));
+        keys.into_iter().map(ToString::to_string).collect()
+    }
+
+    /// Commands with their bound keys, in help order; unbound ones are left out.
+    pub fn help_entries(&self) -> Vec<(String, &'static str)> {
+        DEFAULT_BINDINGS
+            .iter()
+            .filter_map(|(command, description, _)| {
+                let keys = self.keys_for(*command);
+                (!keys.is_empty()).then(|| (keys.join(" / "), *description))
+            })
+            .collect()
+    }
+}
+
+fn command_name(command: Command) -> String {
+    serde_json::to_value(command)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key::new(code, modifiers)
+    }
+
+    fn overrides(entries: &[(Command, &[&str])]) -> BTreeMap<Command, KeyBindings> {
+        entries
+            .iter()
+            .map(|(command, specs)| {
+                let keys = specs.iter().map(|spec| spec.parse().unwrap()).collect();
+                (*command, KeyBindings(keys))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_modifiers_and_named_keys() {
+        let ctrl_alt = KeyModifiers::CONTROL | KeyModifiers::ALT;
+        assert_eq!(parse_token("ctrl+d"), Ok(vec![key(KeyCode::Char('d'), KeyModifiers::CONTROL)]));
+        assert_eq!(parse_token("Ctrl+Alt+x"), Ok(vec![key(KeyCode::Char('x'), ctrl_alt)]));
+        assert_eq!(parse_token("alt+enter"), Ok(vec![key(KeyCode::Enter, KeyModifiers::ALT)]));
+        assert_eq!(parse_token("f5"), Ok(vec![key(KeyCode::F(5), KeyModifiers::NONE)]));
+        // Shift is folded into the character
+        assert_eq!(parse_token("shift+a"), parse_token("A"));
+        // A lone `+` is a key, not a modifier
+        assert_eq!(parse_token("+"), Ok(vec![key(KeyCode::Char('+'), KeyModifiers::NONE)]));
+        assert!(parse_token("ctrl+gg").is_err());
+    }
+
+    #[test]
+    fn shift_tab_is_back_tab() {
+        let back_tab = vec![key(KeyCode::BackTab, KeyModifiers::NONE)];
+        assert_eq!(parse_token("shift+tab"), Ok(back_tab.clone()));
+        let pressed = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(vec![Key::from(pressed)], back_tab);
+        assert_eq!(back_tab[0].to_string(), "shift+tab");
+    }
+
+    #[test]
+    fn chords_parse_and_print_back() {
+        let gg: KeySequence = "gg".parse().unwrap();
+        assert_eq!(gg.0.len(), 2);
+        assert_eq!(gg.to_string(), "gg");
+        let mixed: KeySequence = "ctrl+w j".parse().unwrap();
+        assert_eq!(mixed.0.len(), 2);
+        assert_eq!(mixed.to_string(), "ctrl+w j");
+        assert!("  ".parse::<KeySequence>().is_err());
+    }
+
+    #[test]
+    fn looks_up_chords_key_by_key() {
+        let keymap = Keymap::default();
+        let g = key(KeyCode::Char('g'), KeyModifiers::NONE);
+        let p = key(KeyCode::Char('p'), KeyModifiers::NONE);
+        assert!(matches!(keymap.lookup(&[g]), KeyMatch::Pending));
+        assert!(matches!(keymap.lookup(&[g, g]), KeyMatch::Command(Command::GoToTop)));
+        assert!(matches!(keymap.lookup(&[g, p]), KeyMatch::Command(Command::JumpToPlaying)));
+        assert!(matches!(keymap.lookup(&[p, p]), KeyMatch::None));
+    }
+
+    #[test]
+    fn rebinding_replaces_the_defaults() {
+        let rebound = overrides(&[(Command::Quit, &["ctrl+c"]), (Command::Help, &["q"])]);
+        let keymap = Keymap::new(&rebound).unwrap();
+        assert_eq!(keymap.keys_for(Command::Quit), ["ctrl+c"]);
+        assert_eq!(keymap.keys_for(Command::Help), ["q"]);
+    }
+
+    #[test]
+    fn an_empty_list_unbinds() {
+        let keymap = Keymap::new(&overrides(&[(Command::Palette, &[])])).unwrap();
+        assert!(keymap.keys_for(Command::Palette).is_empty());
+        let colon = key(KeyCode::Char(':'), KeyModifiers::NONE);
+        assert!(matches!(keymap.lookup(&[colon]), KeyMatch::None));
+        assert!(keymap.help_entries().iter().all(|(_, text)| *text != "Command palette"));
+    }
+
+    #[test]
+    fn rejects_conflicts() {
+        let both = overrides(&[(Command::Quit, &["x"]), (Command::Help, &["x"])]);
+        let error = Keymap::new(&both).unwrap_err();
+        assert!(error.contains("is bound to both"), "{}", error);
+
+        // `g` would shadow the default `gg` and `gp`
+        let prefix = overrides(&[(Command::Quit, &["g"])]);
+        let error = Keymap::new(&prefix).unwrap_err();
+        assert!(error.contains("is a prefix of"), "{}", error);
+    }
+}
@@ -1,36 +1,116 @@
 mod action;
 mod app;
 mod auth;
+mod cache;
+mod cli;
 mod config;
 mod error;
 mod event;
+mod history;
+mod hooks;
+mod http;
+mod keymap;
+#[cfg(target_os = "linux")]
+mod mpris;
+#[cfg(target_os = "linux")]
+mod notify;
+mod palette;
+mod party;
+mod scrobble;
+#[cfg(unix)]
+mod socket;
 mod spotify;
+mod statusline;
 mod ui;
 
 use std::io;
-use std::time::Duration;
 
-use anyhow::Result;
-use crossterm::event::KeyCode;
+use anyhow::{bail, Context, Result};
+use crossterm::event::{
+    DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, KeyCode,
+    KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 use crossterm::ExecutableCommand;
 use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Position, Rect};
 use ratatui::Terminal;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 
 use action::{Action, Event, IoEvent};
 use app::{App, InputMode, Screen};
+use cache::LibraryCache;
+use config::AppConfig;
+use error::AppError;
 use event::EventHandler;
+use history::{ListeningHistory, Stats};
+use keymap::Command;
 use spotify::SpotifyClient;
+use ui::hit_test::{self, Target};
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let mut headless = false;
+    let mut profile = None;
+    let mut args = Vec::new();
+    let mut raw_args = std::env::args().skip(1);
+    while let Some(arg) = raw_args.next() {
+        if arg == "--headless" {
+            headless = true;
+        } else if arg == "--profile" {
+            profile = Some(raw_args.next().context("--profile needs a profile name")?);
+        } else if let Some(name) = arg.strip_prefix("--profile=") {
+            profile = Some(name.to_string());
+        } else {
+            args.push(arg);
+        }
+    }
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let mut cli_command = None;
+    match args.as_slice() {
+        [] => {}
+        ["help"] | ["--help"] | ["-h"] => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        ["cache", "clear"] => {
+            LibraryCache::clear()?;
+            eprintln!("Library cache cleared.");
+            return Ok(());
+        }
+        ["logout"] => {
+            return auth::logout(&AppConfig::load_unvalidated(profile.as_deref())?);
+        }
+        ["config", "init"] => {
+            let path = AppConfig::write_default()?;
+            eprintln!("Wrote default config to {}", path.display());
+            return Ok(());
+        }
+        args => match cli::parse(args)? {
+            Some(command) => cli_command = Some(command),
+            None => bail!("{}", cli::USAGE),
+        },
+    }
+
+    let config = AppConfig::load(profile.as_deref())?;
+
+    if let Some(command) = cli_command {
+        return cli::run(&config, headless, command).await;
+    }
+
     // Authenticate with Spotify
     eprintln!("Authenticating with Spotify...");
-    let spotify_auth = auth::authenticate().await?;
-    let spotify_client = SpotifyClient::new(spotify_auth);
+    let (spotify_auth, login_flow) = auth::authenticate(&config, headless).await?;
+    let mut spotify_client = SpotifyClient::new(spotify_auth, login_flow, config.market());
+
+    let remote_server = match config.http.enabled {
+        true => Some(http::RemoteServer::bind(&config.http, &config.party)?),
+        false => None,
+    };
 
     // Verify connection
     eprintln!("Connected! Starting TUI...");
@@ -39,6 +119,10 @@ async fn main() -> Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     stdout.execute(EnterAlternateScreen)?;
+    stdout.execute(EnableFocusChange)?;
+    if config.mouse {
+        stdout.execute(EnableMouseCapture)?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
@@ -47,17 +131,139 @@ async fn main() -> Result<()> {
     let (action_tx, mut action_rx) = mpsc::unbounded_channel::<Action>();
 
     // Create app
-    let mut app = App::new(io_tx);
+    let settings = config.settings();
+    let mut app = App::new(io_tx, settings.clone());
+    app.profile = config.profile_name().to_string();
+    app.profiles = config.profile_names();
     app.init();
 
     // Create event handler
-    let mut events = EventHandler::new(Duration::from_millis(250));
+    let mut events = EventHandler::new(settings.tick_rate);
+
+    // Desktop media keys and status bars; without a session bus there are
+    // simply no MPRIS clients to serve
+    #[cfg(target_os = "linux")]
+    let mpris_state = config.mpris.then(|| {
+        let (state_tx, state_rx) = watch::channel(mpris::PlayerState::default());
+        let events_tx = events.sender();
+        tokio::spawn(async move {
+            let _ = mpris::serve(events_tx, state_rx).await;
+        });
+        state_tx
+    });
+
+    // What's playing, for when the terminal is in the background
+    #[cfg(target_os = "linux")]
+    let notifier = config.notifications.enabled.then(|| {
+        let (tracks_tx, tracks_rx) = watch::channel(None);
+        let min_interval = std::time::Duration::from_secs(config.notifications.min_interval_secs);
+        tokio::spawn(async move {
+            let _ = notify::serve(tracks_rx, min_interval).await;
+        });
+        tracks_tx
+    });
+    #[cfg(target_os = "linux")]
+    let notify_when_focused = config.notifications.when_focused;
+
+    // The status the control socket and web remote report; closed if
+    // neither is running
+    let (status_tx, status_rx) = watch::channel(serde_json::Value::Null);
+
+    // Commands from scripts and `crabify play` & co. while the TUI runs
+    #[cfg(unix)]
+    if config.control_socket {
+        let events_tx = events.sender();
+        let status_rx = status_rx.clone();
+        let path = config.socket_path()?;
+        tokio::spawn(async move {
+            let _ = socket::serve(path, events_tx, status_rx).await;
+        });
+    }
+
+    // Phones and other machines on the network
+    let (party_tx, party_rx) = watch::channel(serde_json::Value::Null);
+    let (client_tx, client_rx) = watch::channel(spotify_client.clone());
+    match remote_server {
+        Some(server) => server.serve(events.sender(), status_rx, party_rx, client_rx),
+        None => drop((status_rx, party_rx, client_rx)),
+    }
+
+    // Listens for ListenBrainz, submitted in the background
+    let scrobbler = match config.scrobble.enabled {
+        true => {
+            let (scrobble_tx, scrobble_rx) = mpsc::unbounded_channel();
+            let scrobble_config = config.scrobble.clone();
+            let queue_path = config.scrobble_queue_path()?;
+            let actions = action_tx.clone();
+            tokio::spawn(async move {
+                let scrobbler = scrobble::serve(scrobble_config, queue_path, scrobble_rx, actions);
+                let _ = scrobbler.await;
+            });
+            Some(scrobble_tx)
+        }
+        false => None,
+    };
+
+    let hook_commands = config.hooks.clone();
 
     // Spawn network handler task
     let net_action_tx = action_tx.clone();
     tokio::spawn(async move {
-        while let Some(io_event) = io_rx.recv().await {
-            let result = handle_io_event(&spotify_client, io_event).await;
+        // The cache is best-effort; without it everything is fetched fresh
+        let mut cache = LibraryCache::open(&config).ok();
+        let mut history = ListeningHistory::open(config.profile_name()).ok();
+        // Logins run on their own, so that polling and playback carry on
+        // while the browser is out
+        let (switched_tx, mut switched_rx) = mpsc::unbounded_channel();
+        loop {
+            let result = tokio::select! {
+                io_event = io_rx.recv() => match io_event {
+                    Some(IoEvent::Reauthenticate) => {
+                        let client = spotify_client.clone();
+                        let actions = net_action_tx.clone();
+                        tokio::spawn(async move {
+                            let show_url = |url| {
+                                let _ = actions.send(Action::LoginStarted(url));
+                            };
+                            let result = match client.reauthenticate(show_url).await {
+                                Ok(()) => Action::Authenticated,
+                                Err(e) => auth_failed("Failed to log in", e),
+                            };
+                            let _ = actions.send(result);
+                        });
+                        continue;
+                    }
+                    Some(IoEvent::SwitchProfile(profile)) => {
+                        let switched = switched_tx.clone();
+                        let actions = net_action_tx.clone();
+                        tokio::spawn(async move {
+                            match switch_profile(&profile, &actions).await {
+                                Ok((client, config)) => {
+                                    let _ = switched.send((profile, client, config));
+                                }
+                                Err(e) => {
+                                    let failure = auth_failed("Failed to switch account", e);
+                                    let _ = actions.send(failure);
+                                }
+                            }
+                        });
+                        continue;
+                    }
+                    Some(io_event) => {
+                        handle_io_event(&mut spotify_client, &mut cache, &mut history, io_event)
+                            .await
+                    }
+                    None => break,
+                },
+                Some((profile, client, config)) = switched_rx.recv() => {
+                    spotify_client = client;
+                    cache = LibraryCache::open(&config).ok();
+                    history = ListeningHistory::open(config.profile_name()).ok();
+                    // The web remote follows the account the TUI switched to
+                    client_tx.send_replace(spotify_client.clone());
+                    Action::ProfileSwitched(profile)
+                }
+            };
             let _ = net_action_tx.send(result);
         }
     });
@@ -65,6 +271,9 @@ async fn main() -> Result<()> {
     // Main loop
     loop {
         // Draw
+        let size = terminal.size()?;
+        let area = Rect::new(0, 0, size.width, size.height);
+        app.set_viewport(area);
         terminal.draw(|f| ui::render(f, &app))?;
 
         // Handle events
@@ -74,12 +283,24 @@ async fn main() -> Result<()> {
                     Event::Key(key) => {
                         handle_key_event(&mut app, key);
                     }
+                    Event::Mouse(mouse) => {
+                        handle_mouse_event(&mut app, mouse, area);
+                    }
                     Event::Tick => {
                         app.on_tick();
                     }
-                    Event::Resize(_, _) => {
+                    Event::Resize => {
                         // Terminal will re-draw automatically
                     }
+                    Event::FocusGained => {
+                        app.on_focus_change(true);
+                    }
+                    Event::FocusLost => {
+                        app.on_focus_change(false);
+                    }
+                    Event::Remote(remote) => {
+                        app.on_remote(remote);
+                    }
                 }
             }
             Some(action) = action_rx.recv() => {
@@ -87,13 +308,57 @@ async fn main() -> Result<()> {
             }
         }
 
+        for (hook, payload) in std::mem::take(&mut app.fired_hooks) {
+            #[cfg(target_os = "linux")]
+            if let Some(notifier) = &notifier {
+                if hook == hooks::Hook::TrackChanged && (notify_when_focused || !app.is_focused()) {
+                    notifier.send_replace(Some(payload.clone()));
+                }
+            }
+            let Some(command) = hook_commands.command(hook) else {
+                continue;
+            };
+            if let Err(e) = hooks::run(command, hook, &payload) {
+                app.update(Action::Error {
+                    context: format!("The {} hook failed", hook.name()),
+                    error: e.into(),
+                });
+            }
+        }
+
+        for scrobble in std::mem::take(&mut app.scrobbles) {
+            if let Some(scrobbler) = &scrobbler {
+                let _ = scrobbler.send(scrobble);
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(state) = &mpris_state {
+            state.send_replace(mpris::PlayerState::from_app(&app));
+        }
+        if !status_tx.is_closed() {
+            status_tx.send_replace(app.status_json());
+        }
+        if !party_tx.is_closed() {
+            party_tx.send_replace(app.party.to_json());
+        }
+
         if !app.running {
             break;
         }
     }
 
+    // The track playing on exit counts as far as it got
+    if let Some(play) = app.finish_play() {
+        if let Ok(history) = ListeningHistory::open(&app.profile) {
+            let _ = history.record(&play);
+        }
+    }
+
     // Restore terminal
     disable_raw_mode()?;
+    io::stdout().execute(DisableMouseCapture)?;
+    io::stdout().execute(DisableFocusChange)?;
     io::stdout().execute(LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
@@ -101,6 +366,39 @@ async fn main() -> Result<()> {
 }
 
 fn handle_key_event(app: &mut App, key: crossterm::event::KeyEvent) {
+    // The command palette takes text input too
+    if app.palette.is_some() {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => {
+                app.palette_submit();
+            }
+            KeyCode::Esc => {
+                app.palette = None;
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                app.palette_move(true);
+            }
+            KeyCode::Up | KeyCode::BackTab => {
+                app.palette_move(false);
+            }
+            KeyCode::Char('n') if ctrl => {
+                app.palette_move(true);
+            }
+            KeyCode::Char('p') if ctrl => {
+                app.palette_move(false);
+            }
+            KeyCode::Char(c) => {
+                app.palette_input(c);
+            }
+            KeyCode::Backspace => {
+                app.palette_backspace();
+            }
+            _ => {}
+        }
+        return;
+    }
+
     // In editing mode, handle text input
     if app.input_mode == InputMode::Editing {
         match key.code {
@@ -121,10 +419,17 @@ fn handle_key_event(app: &mut App, key: crossterm::event::KeyEvent) {
         return;
     }
 
+    let Some(command) = app.resolve_key(key.into()) else {
+        return;
+    };
+
     // Help overlay
     if app.show_help {
-        match key.code {
-            KeyCode::Char('?') | KeyCode::Esc => {
+        match command {
+            command if command.is_motion() => {
+                app.move_cursor(command);
+            }
+            Command::Help | Command::Back => {
                 app.show_help = false;
             }
             _ => {}
@@ -132,223 +437,342 @@ fn handle_key_event(app: &mut App, key: crossterm::event::KeyEvent) {
         return;
     }
 
-    // Normal mode
-    match key.code {
-        KeyCode::Char('q') => {
-            app.running = false;
-        }
-        KeyCode::Char('?') => {
-            app.show_help = true;
-        }
+    // The login carries on when its URL is hidden
+    if app.login_url.is_some() && command == Command::Back {
+        app.login_url = None;
+        return;
+    }
 
-        // Screen navigation
-        KeyCode::BackTab => {
-            app.prev_screen();
-        }
-        KeyCode::Tab => {
-            if app.screen == Screen::Library {
-                app.toggle_panel();
-            } else {
-                app.next_screen();
+    // Account picker overlay
+    if app.show_profiles {
+        match command {
+            command if command.is_motion() => {
+                app.move_cursor(command);
+            }
+            Command::Select => {
+                app.switch_to_selected_profile();
             }
+            Command::Accounts | Command::Back => {
+                app.show_profiles = false;
+            }
+            _ => {}
         }
+        return;
+    }
 
-        // List navigation
-        KeyCode::Char('j') | KeyCode::Down => {
-            app.move_down();
-        }
-        KeyCode::Char('k') | KeyCode::Up => {
-            app.move_up();
-        }
-        KeyCode::Enter => {
-            app.on_enter();
+    // Party queue overlay
+    if app.show_party {
+        match command {
+            command if command.is_motion() => {
+                app.move_cursor(command);
+            }
+            Command::Select => {
+                app.queue_selected_suggestion();
+            }
+            Command::Remove => {
+                app.remove_selected_suggestion();
+            }
+            Command::PartyQueue | Command::Back => {
+                app.show_party = false;
+            }
+            _ => {}
         }
+        return;
+    }
 
-        // Search
-        KeyCode::Char('/') => {
-            app.screen = Screen::Search;
-            app.input_mode = InputMode::Editing;
-            app.search_input.clear();
+    // Device picker overlay
+    if app.show_devices {
+        match command {
+            command if command.is_motion() => {
+                app.move_cursor(command);
+            }
+            Command::Select => {
+                app.transfer_to_selected_device();
+            }
+            Command::Devices | Command::Back => {
+                app.show_devices = false;
+            }
+            _ => {}
         }
+        return;
+    }
 
-        // Playback controls
-        KeyCode::Char(' ') => {
-            app.dispatch_io(IoEvent::PlayPause);
-        }
-        KeyCode::Char('n') => {
-            app.dispatch_io(IoEvent::NextTrack);
-        }
-        KeyCode::Char('p') => {
-            app.dispatch_io(IoEvent::PreviousTrack);
+    app.run_command(command);
+}
+
+fn handle_mouse_event(app: &mut App, mouse: MouseEvent, area: Rect) {
+    let position = Position::new(mouse.column, mouse.row);
+    match mouse.kind {
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp if app.palette.is_some() => {
+            app.palette_move(mouse.kind == MouseEventKind::ScrollDown);
         }
-        KeyCode::Char('+') | KeyCode::Char('=') => {
-            app.dispatch_io(IoEvent::VolumeUp);
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+            // Scroll whichever library panel is under the pointer
+            let overlay = app.show_devices || app.show_profiles || app.show_party || app.show_help;
+            if app.screen == Screen::Library && !overlay {
+                if let Some(panel) = hit_test::panel_at(area, position) {
+                    app.active_panel = panel;
+                }
+            }
+            if mouse.kind == MouseEventKind::ScrollDown {
+                app.move_down();
+            } else {
+                app.move_up();
+            }
         }
-        KeyCode::Char('-') => {
-            app.dispatch_io(IoEvent::VolumeDown);
+        MouseEventKind::Down(MouseButton::Left) => {
+            let target = hit_test::target_at(app, area, position);
+            let double = app.register_click(mouse.column, mouse.row);
+            if app.input_mode == InputMode::Editing && target != Some(Target::SearchInput) {
+                app.input_mode = InputMode::Normal;
+            }
+            match target {
+                Some(Target::Tab(screen)) => {
+                    app.go_to_screen(screen);
+                }
+                Some(Target::Row(panel, index)) => {
+                    app.active_panel = panel;
+                    app.select_row(index);
+                    if double {
+                        app.on_enter();
+                    }
+                }
+                Some(Target::SearchInput) => {
+                    app.input_mode = InputMode::Editing;
+                }
+                Some(Target::ProgressBar(fraction)) => {
+                    app.seek(fraction);
+                }
+                Some(Target::Device(index)) => {
+                    app.device_index = index;
+                    if double {
+                        app.transfer_to_selected_device();
+                    }
+                }
+                Some(Target::Suggestion(index)) => {
+                    app.party_index = index;
+                    if double {
+                        app.queue_selected_suggestion();
+                    }
+                }
+                Some(Target::Profile(index)) => {
+                    app.profile_index = index;
+                    if double {
+                        app.switch_to_selected_profile();
+                    }
+                }
+                None => {}
+            }
         }
+        _ => {}
+    }
+}
 
-        // Like toggle
-        KeyCode::Char('s') => {
-            app.toggle_like();
-        }
-        KeyCode::Char('l') => {
-            app.toggle_like_now_playing();
-        }
+/// Loads `profile`'s config and logs in to it, showing the login URL in
+/// the app through `actions` if it has to.
+async fn switch_profile(
+    profile: &str,
+    actions: &mpsc::UnboundedSender<Action>,
+) -> error::Result<(SpotifyClient, AppConfig)> {
+    let config =
+        AppConfig::load(Some(profile)).map_err(|e| AppError::Auth(e.to_string()))?;
+    let show_url = |url| {
+        let _ = actions.send(Action::LoginStarted(url));
+    };
+    let (spotify_auth, login_flow) = auth::authenticate_in_app(&config, show_url)
+        .await
+        .map_err(|e| AppError::Auth(e.to_string()))?;
+    let market = config.market();
+    Ok((SpotifyClient::new(spotify_auth, login_flow, market), config))
+}
 
-        _ => {}
+fn failed(context: &str, error: AppError) -> Action {
+    Action::Error {
+        context: context.to_string(),
+        error,
+    }
+}
+
+fn auth_failed(context: &str, error: AppError) -> Action {
+    Action::AuthenticationFailed {
+        context: context.to_string(),
+        error,
     }
 }
 
-async fn handle_io_event(client: &SpotifyClient, event: IoEvent) -> Action {
+/// Playback commands don't fetch state themselves; the app reconciles
+/// its optimistic state with a follow-up poll.
+fn playback_command(context: &str, result: error::Result<()>) -> Action {
+    match result {
+        Ok(()) => Action::PlaybackCommandSucceeded,
+        Err(error) => Action::PlaybackCommandFailed {
+            context: context.to_string(),
+            error,
+        },
+    }
+}
+
+async fn fetch_now_playing(client: &SpotifyClient) -> Action {
+    match client.fetch_now_playing().await {
+        Ok(ctx) => Action::NowPlayingUpdated(ctx.map(Box::new)),
+        Err(e) => failed("Failed to fetch playback", e),
+    }
+}
+
+async fn handle_io_event(
+    client: &mut SpotifyClient,
+    library_cache: &mut Option<LibraryCache>,
+    history: &mut Option<ListeningHistory>,
+    event: IoEvent,
+) -> Action {
+    let cache = library_cache.as_ref();
     match event {
-        IoEvent::FetchNowPlaying => match client.fetch_now_playing().await {
-            Ok(ctx) => Action::NowPlayingUpdated(ctx),
-            Err(e) => Action::Error(format!("Failed to fetch playback: {}", e)),
+        IoEvent::LoadCachedLibrary => Action::CachedLibraryLoaded {
+            playlists: cache.and_then(|c| c.load_playlists()).unwrap_or_default(),
+            liked_songs: cache.and_then(|c| c.load_liked_songs()).unwrap_or_default(),
         },
-        IoEvent::PlayPause => {
-            // First fetch current state to know if playing
-            match client.fetch_now_playing().await {
-                Ok(Some(ctx)) => {
-                    let is_playing = ctx.is_playing;
-                    match client.play_pause(is_playing).await {
-                        Ok(()) => {
-                            tokio::time::sleep(Duration::from_millis(200)).await;
-                            match client.fetch_now_playing().await {
-                                Ok(ctx) => Action::NowPlayingUpdated(ctx),
-                                Err(e) => Action::Error(format!("Failed to fetch playback: {}", e)),
-                            }
-                        }
-                        Err(e) => Action::Error(format!("Playback control failed: {}", e)),
-                    }
-                }
-                Ok(None) => Action::Error("No active device found".to_string()),
-                Err(e) => Action::Error(format!("Failed to fetch playback: {}", e)),
-            }
+        IoEvent::FetchNowPlaying => fetch_now_playing(client).await,
+        IoEvent::Pause => playback_command("Pause failed", client.pause().await),
+        IoEvent::Resume => playback_command("Resume failed", client.resume().await),
+        IoEvent::NextTrack => playback_command("Next track failed", client.next_track().await),
+        IoEvent::PreviousTrack => {
+            playback_command("Previous track failed", client.previous_track().await)
         }
-        IoEvent::NextTrack => match client.next_track().await {
-            Ok(()) => {
-                tokio::time::sleep(Duration::from_millis(300)).await;
-                match client.fetch_now_playing().await {
-                    Ok(ctx) => Action::NowPlayingUpdated(ctx),
-                    Err(e) => Action::Error(format!("Failed to fetch playback: {}", e)),
+        IoEvent::SetVolume(volume) => {
+            playback_command("Volume change failed", client.set_volume(volume).await)
+        }
+        IoEvent::Seek(position_ms) => {
+            playback_command("Seek failed", client.seek(position_ms).await)
+        }
+        IoEvent::SetShuffle(state) => {
+            playback_command("Shuffle change failed", client.set_shuffle(state).await)
+        }
+        IoEvent::SetRepeat(state) => {
+            playback_command("Repeat change failed", client.set_repeat(state).await)
+        }
+        IoEvent::FetchPlaylists => match client.fetch_playlists().await {
+            Ok(playlists) => {
+                if let Some(cache) = cache {
+                    let _ = cache.save_playlists(&playlists);
                 }
+                Action::PlaylistsLoaded(playlists)
             }
-            Err(e) => Action::Error(format!("Next track failed: {}", e)),
+            Err(e) => failed("Failed to fetch playlists", e),
         },
-        IoEvent::PreviousTrack => match client.previous_track().await {
-            Ok(()) => {
-                tokio::time::sleep(Duration::from_millis(300)).await;
-                match client.fetch_now_playing().await {
-                    Ok(ctx) => Action::NowPlayingUpdated(ctx),
-                    Err(e) => Action::Error(format!("Failed to fetch playback: {}", e)),
-                }
+        IoEvent::FetchPlaylistTracks {
+            playlist_id,
+            snapshot_id,
+        } => {
+            // Tracks only change when the playlist's snapshot does
+            let cached =
+                cache.and_then(|c| c.load_playlist_tracks(&playlist_id, &snapshot_id));
+            if let Some(tracks) = cached {
+                return Action::PlaylistTracksLoaded(tracks);
             }
-            Err(e) => Action::Error(format!("Previous track failed: {}", e)),
-        },
-        IoEvent::VolumeUp => {
-            match client.fetch_now_playing().await {
-                Ok(Some(ctx)) => {
-                    let current = ctx.device.volume_percent.unwrap_or(50) as u8;
-                    let new_vol = (current + 5).min(100);
-                    match client.set_volume(new_vol).await {
-                        Ok(()) => {
-                            tokio::time::sleep(Duration::from_millis(200)).await;
-                            match client.fetch_now_playing().await {
-                                Ok(ctx) => Action::NowPlayingUpdated(ctx),
-                                Err(e) => Action::Error(format!("{}", e)),
-                            }
-                        }
-                        Err(e) => Action::Error(format!("Volume change failed: {}", e)),
+            match client.fetch_playlist_tracks(&playlist_id).await {
+                Ok(tracks) => {
+                    if let Some(cache) = cache {
+                        let _ = cache.save_playlist_tracks(&playlist_id, &snapshot_id, &tracks);
                     }
+                    Action::PlaylistTracksLoaded(tracks)
                 }
-                Ok(None) => Action::Error("No active device".to_string()),
-                Err(e) => Action::Error(format!("{}", e)),
-            }
-        }
-        IoEvent::VolumeDown => {
-            match client.fetch_now_playing().await {
-                Ok(Some(ctx)) => {
-                    let current = ctx.device.volume_percent.unwrap_or(50) as u8;
-                    let new_vol = current.saturating_sub(5);
-                    match client.set_volume(new_vol).await {
-                        Ok(()) => {
-                            tokio::time::sleep(Duration::from_millis(200)).await;
-                            match client.fetch_now_playing().await {
-                                Ok(ctx) => Action::NowPlayingUpdated(ctx),
-                                Err(e) => Action::Error(format!("{}", e)),
-                            }
-                        }
-                        Err(e) => Action::Error(format!("Volume change failed: {}", e)),
-                    }
-                }
-                Ok(None) => Action::Error("No active device".to_string()),
-                Err(e) => Action::Error(format!("{}", e)),
+                Err(e) => failed("Failed to fetch tracks", e),
             }
         }
-        IoEvent::FetchPlaylists => match client.fetch_playlists().await {
-            Ok(playlists) => Action::PlaylistsLoaded(playlists),
-            Err(e) => Action::Error(format!("Failed to fetch playlists: {}", e)),
-        },
-        IoEvent::FetchPlaylistTracks(id) => match client.fetch_playlist_tracks(&id).await {
-            Ok(tracks) => Action::PlaylistTracksLoaded(tracks),
-            Err(e) => Action::Error(format!("Failed to fetch tracks: {}", e)),
-        },
-        IoEvent::PlayTrackInContext { context_uri, offset } => {
-            match client.play_track_in_context(&context_uri, offset).await {
-                Ok(()) => {
-                    tokio::time::sleep(Duration::from_millis(300)).await;
-                    match client.fetch_now_playing().await {
-                        Ok(ctx) => Action::NowPlayingUpdated(ctx),
-                        Err(e) => Action::Error(format!("{}", e)),
-                    }
-                }
-                Err(e) => Action::Error(format!("Failed to play track: {}", e)),
-            }
+        IoEvent::PlayTrackInContext { context_uri, offset } => playback_command(
+            "Failed to play track",
+            client.play_track_in_context(&context_uri, offset).await,
+        ),
+        IoEvent::PlayTrack(uri) => {
+            playback_command("Failed to play track", client.play_track(&uri).await)
         }
-        IoEvent::PlayTrack(uri) => match client.play_track(&uri).await {
-            Ok(()) => {
-                tokio::time::sleep(Duration::from_millis(300)).await;
-                match client.fetch_now_playing().await {
-                    Ok(ctx) => Action::NowPlayingUpdated(ctx),
-                    Err(e) => Action::Error(format!("{}", e)),
-                }
-            }
-            Err(e) => Action::Error(format!("Failed to play track: {}", e)),
-        },
         IoEvent::Search(query) => match client.search_tracks(&query).await {
             Ok(tracks) => Action::SearchResultsLoaded { tracks },
-            Err(e) => Action::Error(format!("Search failed: {}", e)),
+            Err(e) => failed("Search failed", e),
         },
         IoEvent::FetchLikedSongs => match client.fetch_liked_songs().await {
-            Ok(songs) => Action::LikedSongsLoaded(songs),
-            Err(e) => Action::Error(format!("Failed to fetch liked songs: {}", e)),
+            Ok(songs) => {
+                if let Some(cache) = cache {
+                    let _ = cache.save_liked_songs(&songs);
+                }
+                Action::LikedSongsLoaded(songs)
+            }
+            Err(e) => failed("Failed to fetch liked songs", e),
         },
         IoEvent::ToggleLike {
             track_id,
+            track,
             currently_liked,
         } => {
             if currently_liked {
                 match client.remove_track(&track_id).await {
-                    Ok(()) => Action::LikeToggled {
-                        track_id,
-                        is_liked: false,
-                    },
-                    Err(e) => Action::Error(format!("Failed to unlike: {}", e)),
+                    Ok(()) => {
+                        if let Some(cache) = cache {
+                            let _ = cache.remove_liked_song(&track_id);
+                        }
+                        Action::LikeToggled {
+                            track_id,
+                            is_liked: false,
+                        }
+                    }
+                    Err(e) => failed("Failed to unlike", e),
                 }
             } else {
                 match client.save_track(&track_id).await {
-                    Ok(()) => Action::LikeToggled {
-                        track_id,
-                        is_liked: true,
-                    },
-                    Err(e) => Action::Error(format!("Failed to like: {}", e)),
+                    Ok(()) => {
+                        // Without the track, the cache catches up on the next fetch
+                        if let (Some(cache), Some(track)) = (cache, track) {
+                            let _ = cache.add_liked_song(*track);
+                        }
+                        Action::LikeToggled {
+                            track_id,
+                            is_liked: true,
+                        }
+                    }
+                    Err(e) => failed("Failed to like", e),
                 }
             }
         }
         IoEvent::FetchDevices => match client.fetch_devices().await {
             Ok(devices) => Action::DevicesLoaded(devices),
-            Err(e) => Action::Error(format!("Failed to fetch devices: {}", e)),
+            Err(e) => failed("Failed to fetch devices", e),
+        },
+        IoEvent::TransferPlayback(device_id) => playback_command(
+            "Failed to transfer playback",
+            client.transfer_playback(&device_id).await,
+        ),
+        IoEvent::AddToPlaylist {
+            playlist_id,
+            track_id,
+        } => match client.add_to_playlist(&playlist_id, &track_id).await {
+            Ok(snapshot_id) => Action::AddedToPlaylist {
+                playlist_id,
+                snapshot_id,
+            },
+            Err(e) => failed("Failed to add to playlist", e),
+        },
+        IoEvent::AddToQueue { track_uri, name } => match client.add_to_queue(&track_uri).await {
+            Ok(()) => Action::AddedToQueue(name),
+            Err(e) => failed("Failed to add to queue", e),
         },
+        IoEvent::RecordPlay(play) => {
+            let recorded = history.as_ref().map(|history| history.record(&play));
+            match recorded {
+                Some(Err(e)) => failed("Failed to record listening history", e.into()),
+                _ => Action::PlayRecorded,
+            }
+        }
+        IoEvent::LoadStats(period) => {
+            let plays = history.as_ref().map(ListeningHistory::load).unwrap_or_default();
+            let now = chrono::Utc::now().timestamp();
+            Action::StatsLoaded(Box::new(Stats::new(&plays, period, now)))
+        }
+        IoEvent::RefreshToken => match client.refresh_token().await {
+            Ok(()) => Action::Authenticated,
+            Err(e) => auth_failed("Failed to refresh session", e),
+        },
+        IoEvent::Reauthenticate | IoEvent::SwitchProfile(_) => {
+            unreachable!("logins are handled by the network task")
+        }
     }
 }
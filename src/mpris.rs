@@ -0,0 +1,549 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use anyhow::Result;
+use rspotify::model::{PlayableItem, RepeatState};
+use rspotify::prelude::Id;
+use tokio::sync::{mpsc, watch};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{connection, fdo, interface};
+
+use crate::action::{Event, Remote};
+use crate::app::App;
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.crabify";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+// Position jumps bigger than this between updates are announced as seeks
+const SEEK_TOLERANCE_US: i64 = 2_000_000;
+
+/// The item playing, as MPRIS describes it.
+#[derive(Debug, Clone, PartialEq)]
+struct Track {
+    /// An object path unique to the item, e.g. `/org/crabify/track/<id>`.
+    path: String,
+    title: String,
+    artists: Vec<String>,
+    album: String,
+    album_artists: Vec<String>,
+    art_url: Option<String>,
+    url: Option<String>,
+    length_us: i64,
+}
+
+/// What the player interface publishes, taken from the app after every
+/// event.
+#[derive(Debug, Clone)]
+pub struct PlayerState {
+    track: Option<Track>,
+    playing: bool,
+    volume: u8,
+    shuffle: bool,
+    repeat: RepeatState,
+    position_us: i64,
+    taken_at: Instant,
+}
+
+impl Default for PlayerState {
+    fn default() -> Self {
+        Self {
+            track: None,
+            playing: false,
+            volume: 0,
+            shuffle: false,
+            repeat: RepeatState::Off,
+            position_us: 0,
+            taken_at: Instant::now(),
+        }
+    }
+}
+
+impl PlayerState {
+    pub fn from_app(app: &App) -> Self {
+        let ctx = app.now_playing.as_ref();
+        let track = ctx.and_then(|ctx| ctx.item.as_ref()).map(|item| match item {
+            PlayableItem::Track(track) => Track {
+                path: match &track.id {
+                    Some(id) => format!("/org/crabify/track/{}", id.id()),
+                    None => NO_TRACK.to_string(),
+                },
+                title: track.name.clone(),
+                artists: track.artists.iter().map(|a| a.name.clone()).collect(),
+                album: track.album.name.clone(),
+                album_artists: track.album.artists.iter().map(|a| a.name.clone()).collect(),
+                art_url: track.album.images.first().map(|image| image.url.clone()),
+                url: track.external_urls.get("spotify").cloned(),
+                length_us: track.duration.num_microseconds().unwrap_or(0),
+            },
+            PlayableItem::Episode(episode) => Track {
+                path: format!("/org/crabify/episode/{}", episode.id.id()),
+                title: episode.name.clone(),
+                artists: vec![episode.show.publisher.clone()],
+                album: episode.show.name.clone(),
+                album_artists: vec![episode.show.publisher.clone()],
+                art_url: episode.images.first().map(|image| image.url.clone()),
+                url: episode.external_urls.get("spotify").cloned(),
+                length_us: episode.duration.num_microseconds().unwrap_or(0),
+            },
+        });
+        let position_ms = app.interpolated_progress_ms().map_or(0, |(progress, _)| progress);
+
+        Self {
+            track,
+            playing: app.is_playing,
+            volume: app.volume,
+            shuffle: ctx.is_some_and(|ctx| ctx.shuffle_state),
+            repeat: ctx.map_or(RepeatState::Off, |ctx| ctx.repeat_state),
+            position_us: position_ms * 1000,
+            taken_at: Instant::now(),
+        }
+    }
+
+    /// The playback position now, running on from when the state was taken.
+    fn position_at(&self, at: Instant) -> i64 {
+        if !self.playing {
+            return self.position_us;
+        }
+        let elapsed = at.saturating_duration_since(self.taken_at).as_micros() as i64;
+        let length = self.track.as_ref().map_or(i64::MAX, |track| track.length_us);
+        (self.position_us + elapsed).min(length)
+    }
+
+    fn playback_status(&self) -> &'static str {
+        match (&self.track, self.playing) {
+            (None, _) => "Stopped",
+            (Some(_), true) => "Playing",
+            (Some(_), false) => "Paused",
+        }
+    }
+
+    fn loop_status(&self) -> &'static str {
+        match self.repeat {
+            RepeatState::Off => "None",
+            RepeatState::Context => "Playlist",
+            RepeatState::Track => "Track",
+        }
+    }
+
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        let Some(track) = &self.track else {
+            let path = ObjectPath::from_static_str_unchecked(NO_TRACK);
+            insert(&mut metadata, "mpris:trackid", path);
+            return metadata;
+        };
+        if let Ok(path) = ObjectPath::try_from(track.path.as_str()) {
+            insert(&mut metadata, "mpris:trackid", path);
+        }
+        insert(&mut metadata, "mpris:length", track.length_us);
+        insert(&mut metadata, "xesam:title", track.title.as_str());
+        insert(&mut metadata, "xesam:artist", track.artists.clone());
+        insert(&mut metadata, "xesam:album", track.album.as_str());
+        insert(&mut metadata, "xesam:albumArtist", track.album_artists.clone());
+        if let Some(url) = &track.art_url {
+            insert(&mut metadata, "mpris:artUrl", url.as_str());
+        }
+        if let Some(url) = &track.url {
+            insert(&mut metadata, "xesam:url", url.as_str());
+        }
+        metadata
+    }
+}
+
+fn insert<'a>(
+    metadata: &mut HashMap<String, OwnedValue>,
+    key: &str,
+    value: impl Into<Value<'a>>,
+) {
+    if let Ok(value) = OwnedValue::try_from(value.into()) {
+        metadata.insert(key.to_string(), value);
+    }
+}
+
+/// `org.mpris.MediaPlayer2`: identifies the player. crabify has no window
+/// to raise and quits from the TUI only.
+struct MediaPlayer;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl MediaPlayer {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "crabify".to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// `org.mpris.MediaPlayer2.Player`: forwards controls to the main loop as
+/// [`Remote`] events and reads everything else from the latest state.
+struct Player {
+    events: mpsc::UnboundedSender<Event>,
+    state: watch::Receiver<PlayerState>,
+}
+
+impl Player {
+    fn send(&self, remote: Remote) {
+        let _ = self.events.send(Event::Remote(remote));
+    }
+
+    fn has_track(&self) -> bool {
+        self.state.borrow().track.is_some()
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send(Remote::Next);
+    }
+
+    fn previous(&self) {
+        self.send(Remote::Previous);
+    }
+
+    fn pause(&self) {
+        self.send(Remote::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(Remote::PlayPause);
+    }
+
+    /// Spotify has no stop; pausing is the closest thing.
+    fn stop(&self) {
+        self.send(Remote::Pause);
+    }
+
+    fn play(&self) {
+        self.send(Remote::Play);
+    }
+
+    fn seek(&self, offset: i64) {
+        self.send(Remote::Seek(offset / 1000));
+    }
+
+    /// Ignored unless `track_id` is still the current track, as the spec asks.
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        let current = self.state.borrow().track.clone();
+        let Some(track) = current.filter(|track| track.path == track_id.as_str()) else {
+            return;
+        };
+        if (0..=track.length_us).contains(&position) {
+            self.send(Remote::SetPosition(position / 1000));
+        }
+    }
+
+    fn open_uri(&self, _uri: String) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported("Opening URIs is not supported".to_string()))
+    }
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        self.state.borrow().playback_status().to_string()
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> String {
+        self.state.borrow().loop_status().to_string()
+    }
+
+    #[zbus(property)]
+    fn set_loop_status(&self, status: String) -> fdo::Result<()> {
+        let repeat = match status.as_str() {
+            "None" => RepeatState::Off,
+            "Playlist" => RepeatState::Context,
+            "Track" => RepeatState::Track,
+            _ => return Err(fdo::Error::InvalidArgs(format!("Unknown loop status {}", status))),
+        };
+        self.send(Remote::SetRepeat(repeat));
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    /// Spotify plays at one speed only.
+    #[zbus(property)]
+    fn set_rate(&self, _rate: f64) {}
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        self.state.borrow().shuffle
+    }
+
+    #[zbus(property)]
+    fn set_shuffle(&self, shuffle: bool) {
+        self.send(Remote::SetShuffle(shuffle));
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.state.borrow().metadata()
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.state.borrow().volume as f64 / 100.0
+    }
+
+    #[zbus(property)]
+    fn set_volume(&self, volume: f64) {
+        self.send(Remote::SetVolume((volume.clamp(0.0, 1.0) * 100.0).round() as u8));
+    }
+
+    /// Changes continuously, so clients read it when they need it and
+    /// listen for `Seeked` to catch jumps.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.state.borrow().position_at(Instant::now())
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        self.has_track()
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        self.has_track()
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        self.has_track()
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        self.has_track()
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.has_track()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+}
+
+/// Serves the MPRIS interfaces on the session bus until `state`'s sender
+/// goes away, sending controls to the main loop through `events` and
+/// announcing changes to `state`.
+pub async fn serve(
+    events: mpsc::UnboundedSender<Event>,
+    state: watch::Receiver<PlayerState>,
+) -> Result<()> {
+    serve_on(connection::Builder::session()?, events, state).await
+}
+
+/// Like [`serve`], on the bus `builder` connects to.
+async fn serve_on(
+    builder: connection::Builder<'_>,
+    events: mpsc::UnboundedSender<Event>,
+    mut state: watch::Receiver<PlayerState>,
+) -> Result<()> {
+    let player = Player {
+        events,
+        state: state.clone(),
+    };
+    let connection = builder
+        .serve_at(OBJECT_PATH, MediaPlayer)?
+        .serve_at(OBJECT_PATH, player)?
+        .build()
+        .await?;
+
+    // Another crabify has the name; the spec suggests a unique suffix
+    if connection.request_name(BUS_NAME).await.is_err() {
+        let name = format!("{}.instance{}", BUS_NAME, std::process::id());
+        connection.request_name(name).await?;
+    }
+
+    let player = connection
+        .object_server()
+        .interface::<_, Player>(OBJECT_PATH)
+        .await?;
+    let mut last = state.borrow().clone();
+    while state.changed().await.is_ok() {
+        let next = state.borrow_and_update().clone();
+        let emitter = player.signal_emitter();
+        let iface = player.get().await;
+
+        if next.playback_status() != last.playback_status() {
+            iface.playback_status_changed(emitter).await?;
+        }
+        if next.track != last.track {
+            iface.metadata_changed(emitter).await?;
+            if next.track.is_some() != last.track.is_some() {
+                iface.can_go_next_changed(emitter).await?;
+                iface.can_go_previous_changed(emitter).await?;
+                iface.can_play_changed(emitter).await?;
+                iface.can_pause_changed(emitter).await?;
+                iface.can_seek_changed(emitter).await?;
+            }
+        } else {
+            let drift = next.position_us - last.position_at(next.taken_at);
+            if drift.abs() > SEEK_TOLERANCE_US {
+                Player::seeked(emitter, next.position_us).await?;
+            }
+        }
+        if next.volume != last.volume {
+            iface.volume_changed(emitter).await?;
+        }
+        if next.shuffle != last.shuffle {
+            iface.shuffle_changed(emitter).await?;
+        }
+        if next.repeat != last.repeat {
+            iface.loop_status_changed(emitter).await?;
+        }
+        last = next;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+
+    use zbus::Proxy;
+
+    use super::*;
+
+    /// A private bus, so the test doesn't touch the desktop's.
+    struct Bus {
+        daemon: std::process::Child,
+        address: String,
+    }
+
+    impl Bus {
+        /// `None` where there's no `dbus-daemon` to run.
+        fn start() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            let stdout = daemon.stdout.take()?;
+            std::io::BufReader::new(stdout).read_line(&mut address).ok()?;
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn playing() -> PlayerState {
+        PlayerState {
+            track: Some(Track {
+                path: "/org/crabify/track/abc".to_string(),
+                title: "Song".to_string(),
+                artists: vec!["Artist".to_string()],
+                album: "Album".to_string(),
+                album_artists: vec!["Artist".to_string()],
+                art_url: None,
+                url: None,
+                length_us: 200_000_000,
+            }),
+            playing: true,
+            volume: 40,
+            ..PlayerState::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn controls_and_metadata_over_the_bus() {
+        let Some(bus) = Bus::start() else {
+            eprintln!("skipping: dbus-daemon isn't available");
+            return;
+        };
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        let (_state_tx, state) = watch::channel(playing());
+        let builder = connection::Builder::address(bus.address.as_str()).unwrap();
+        tokio::spawn(serve_on(builder, events_tx, state));
+
+        let client = connection::Builder::address(bus.address.as_str())
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let player = Proxy::new(&client, BUS_NAME, OBJECT_PATH, "org.mpris.MediaPlayer2.Player")
+            .await
+            .unwrap();
+        // The server takes its name in the background
+        let mut called = player.call_method("PlayPause", &()).await;
+        for _ in 0..50 {
+            if called.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            called = player.call_method("PlayPause", &()).await;
+        }
+        called.unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv()).await.unwrap();
+        assert!(matches!(event, Some(Event::Remote(Remote::PlayPause))));
+
+        let metadata: HashMap<String, OwnedValue> = player.get_property("Metadata").await.unwrap();
+        let title: String = metadata["xesam:title"].try_clone().unwrap().try_into().unwrap();
+        assert_eq!(title, "Song");
+        let status: String = player.get_property("PlaybackStatus").await.unwrap();
+        assert_eq!(status, "Playing");
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::Value;
+use tokio::sync::watch;
+use zbus::zvariant;
+use zbus::Connection;
+
+use crate::statusline::{self, escape_markup};
+
+const DESTINATION: &str = "org.freedesktop.Notifications";
+const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Shows a notification for each track sent to `tracks`, as the JSON
+/// status, until the sender goes away. Sends at most one every
+/// `min_interval`; tracks skipped in between are never shown. Only fails
+/// without a session bus.
+pub async fn serve(
+    mut tracks: watch::Receiver<Option<Value>>,
+    min_interval: Duration,
+) -> Result<()> {
+    let connection = Connection::session().await?;
+    // Each notification replaces the last rather than stacking up
+    let mut replaces_id = 0u32;
+
+    while tracks.changed().await.is_ok() {
+        let Some(status) = tracks.borrow_and_update().clone() else {
+            continue;
+        };
+        let summary = statusline::field_value("title", &status);
+        let body = statusline::render("{artist}\n{album}", &status);
+        let hints: HashMap<&str, zvariant::Value> = HashMap::new();
+        let reply = connection
+            .call_method(
+                Some(DESTINATION),
+                OBJECT_PATH,
+                Some(DESTINATION),
+                "Notify",
+                &(
+                    "crabify",
+                    replaces_id,
+                    "audio-x-generic",
+                    summary,
+                    escape_markup(&body),
+                    Vec::<&str>::new(),
+                    hints,
+                    -1i32,
+                ),
+            )
+            .await;
+        // A daemon that's missing or failing only costs this notification;
+        // it may be back for the next track
+        if let Ok(id) = reply.and_then(|reply| reply.body().deserialize()) {
+            replaces_id = id;
+        }
+        tokio::time::sleep(min_interval).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::app::Screen;
+use crate::keymap::Command;
+
+/// Something the command palette can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry {
+    /// Anything a key can be bound to.
+    Run(Command),
+    GoTo(Screen),
+    Play,
+    Pause,
+    ToggleShuffle,
+    CycleRepeat,
+    SetVolume,
+    Seek,
+    SearchFor,
+    TransferPlayback,
+    AddToPlaylist,
+    SwitchAccount,
+}
+
+// In the order they are listed before anything is typed
+pub const ENTRIES: &[Entry] = &[
+    Entry::Play,
+    Entry::Pause,
+    Entry::Run(Command::PlayPause),
+    Entry::Run(Command::NextTrack),
+    Entry::Run(Command::PreviousTrack),
+    Entry::ToggleShuffle,
+    Entry::CycleRepeat,
+    Entry::SetVolume,
+    Entry::Run(Command::VolumeUp),
+    Entry::Run(Command::VolumeDown),
+    Entry::Seek,
+    Entry::SearchFor,
+    Entry::AddToPlaylist,
+    Entry::Run(Command::ToggleLike),
+    Entry::Run(Command::ToggleLikeNowPlaying),
+    Entry::Run(Command::JumpToPlaying),
+    Entry::Run(Command::PartyQueue),
+    Entry::TransferPlayback,
+    Entry::SwitchAccount,
+    Entry::GoTo(Screen::Library),
+    Entry::GoTo(Screen::Search),
+    Entry::GoTo(Screen::LikedSongs),
+    Entry::GoTo(Screen::Stats),
+    Entry::Run(Command::Help),
+    Entry::Run(Command::Quit),
+];
+
+impl Entry {
+    pub fn label(self) -> String {
+        match self {
+            Entry::Run(command) => command.description().to_string(),
+            Entry::GoTo(screen) => format!("Go to {}", screen.label()),
+            Entry::Play => "Play".to_string(),
+            Entry::Pause => "Pause".to_string(),
+            Entry::ToggleShuffle => "Toggle shuffle".to_string(),
+            Entry::CycleRepeat => "Cycle repeat mode".to_string(),
+            Entry::SetVolume => "Set volume…".to_string(),
+            Entry::Seek => "Seek to…".to_string(),
+            Entry::SearchFor => "Search for…".to_string(),
+            Entry::TransferPlayback => "Transfer playback to…".to_string(),
+            Entry::AddToPlaylist => "Add track to playlist…".to_string(),
+            Entry::SwitchAccount => "Switch account to…".to_string(),
+        }
+    }
+}
+
+/// An entry waiting for its argument: typed text, or one of `options`.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub entry: Entry,
+    pub title: &'static str,
+    /// Labels and values to choose from.
+    pub options: Vec<(String, String)>,
+    /// Whether the typed text itself is the argument, rather than a choice
+    /// from `options`.
+    pub free_text: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Palette {
+    pub input: String,
+    /// Index into the filtered matches.
+    pub selected: usize,
+    pub prompt: Option<Prompt>,
+}
+
+/// Filters `labels` by `query`, best matches first, returning their indices.
+pub fn filter<S: AsRef<str>>(query: &str, labels: &[S]) -> Vec<usize> {
+    let mut scored: Vec<(i64, usize)> = labels
+        .iter()
+        .enumerate()
+        .filter_map(|(i, label)| fuzzy_score(query, label.as_ref()).map(|score| (score, i)))
+        .collect();
+    // Stable, so equal scores keep their listed order
+    scored.sort_by_key(|(score, _)| -score);
+    scored.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Scores `text` against `query` if the query's characters appear in it in
+/// order, ignoring case. Runs of consecutive characters and matches at the
+/// start of words score higher; gaps score lower.
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last_match: Option<usize> = None;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let offset = text[next..]
+            .iter()
+            .position(|t| t.to_lowercase().eq(q.to_lowercase()))?;
+        let i = next + offset;
+
+        score += 1;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+        match last_match {
+            Some(last) if last + 1 == i => score += 5,
+            Some(last) => score -= (i - last - 1).min(3) as i64,
+            None => score -= i.min(3) as i64,
+        }
+
+        last_match = Some(i);
+        next = i + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_every_character_in_order() {
+        assert!(fuzzy_score("vup", "Volume up").is_some());
+        assert!(fuzzy_score("upv", "Volume up").is_none());
+        assert!(fuzzy_score("volumes", "Volume").is_none());
+    }
+
+    #[test]
+    fn ignores_case_and_spaces() {
+        assert_eq!(fuzzy_score("VOL UP", "volume up"), fuzzy_score("volup", "Volume Up"));
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn prefers_runs_and_word_starts() {
+        let score = |query| fuzzy_score(query, "Next track").unwrap();
+        // A run beats the same letters spread out
+        assert!(score("next") > score("nxtt"));
+        // The start of a word beats the middle of one
+        assert!(fuzzy_score("t", "a top") > fuzzy_score("t", "atop"));
+        assert!(fuzzy_score("play", "Play / Pause") > fuzzy_score("play", "Party play"));
+    }
+}
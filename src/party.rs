@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+
+use rspotify::model::FullTrack;
+use serde_json::{json, Value};
+
+use crate::cli;
+
+/// A track a guest suggested through the web remote.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub id: u64,
+    pub track: FullTrack,
+    pub uri: String,
+    /// Guests who want it, starting with whoever suggested it.
+    pub voters: HashSet<String>,
+}
+
+/// crabify's own queue of guest suggestions, most votes first, fed to
+/// Spotify's queue one track at a time as each track nears its end.
+#[derive(Debug, Default)]
+pub struct PartyQueue {
+    suggestions: Vec<Suggestion>,
+    next_id: u64,
+    /// The item playing when a suggestion was last fed, so each track
+    /// only feeds one.
+    pub fed_during: Option<String>,
+}
+
+impl PartyQueue {
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.suggestions.is_empty()
+    }
+
+    /// Adds `track`, or votes for it if it's already there. Returns false
+    /// for tracks that can't be queued.
+    pub fn suggest(&mut self, guest: String, track: FullTrack) -> bool {
+        let Some(uri) = track.id.as_ref().map(|id| id.to_string()) else {
+            return false;
+        };
+        if let Some(existing) = self.suggestions.iter_mut().find(|s| s.uri == uri) {
+            existing.voters.insert(guest);
+        } else {
+            self.next_id += 1;
+            self.suggestions.push(Suggestion {
+                id: self.next_id,
+                track,
+                uri,
+                voters: HashSet::from([guest]),
+            });
+        }
+        self.sort();
+        true
+    }
+
+    /// Adds `guest`'s vote; each guest gets one per suggestion.
+    pub fn vote(&mut self, guest: String, id: u64) {
+        if let Some(suggestion) = self.suggestions.iter_mut().find(|s| s.id == id) {
+            suggestion.voters.insert(guest);
+            self.sort();
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Suggestion> {
+        (index < self.suggestions.len()).then(|| self.suggestions.remove(index))
+    }
+
+    // Ties keep the order they were suggested in
+    fn sort(&mut self) {
+        self.suggestions.sort_by_key(|s| std::cmp::Reverse(s.voters.len()));
+    }
+
+    /// The queue as the web remote shows it. Voters are included so it
+    /// can tell guests what they've voted for.
+    pub fn to_json(&self) -> Value {
+        let suggestions: Vec<Value> = self
+            .suggestions
+            .iter()
+            .map(|s| {
+                json!({
+                    "id": s.id,
+                    "track": cli::track_json(&s.track),
+                    "votes": s.voters.len(),
+                    "voters": s.voters,
+                })
+            })
+            .collect();
+        Value::Array(suggestions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: &str) -> FullTrack {
+        serde_json::from_value(json!({
+            "id": id,
+            "uri": format!("spotify:track:{}", id),
+            "name": id,
+            "duration_ms": 1000,
+            "artists": [],
+            "album": {
+                "name": "Album",
+                "artists": [],
+                "images": [],
+                "external_urls": {},
+                "album_type": "album",
+            },
+            "available_markets": [],
+            "disc_number": 1,
+            "explicit": false,
+            "external_ids": {},
+            "external_urls": {},
+            "is_local": false,
+            "popularity": 0,
+            "track_number": 1,
+            "href": null,
+            "preview_url": null,
+        }))
+        .expect("valid track")
+    }
+
+    fn names(queue: &PartyQueue) -> Vec<&str> {
+        queue.suggestions().iter().map(|s| s.track.name.as_str()).collect()
+    }
+
+    #[test]
+    fn suggesting_again_is_a_vote() {
+        let mut queue = PartyQueue::default();
+        assert!(queue.suggest("ann".to_string(), track("a")));
+        assert!(queue.suggest("bob".to_string(), track("a")));
+        assert!(queue.suggest("bob".to_string(), track("a")));
+        assert_eq!(names(&queue), ["a"]);
+        assert_eq!(queue.suggestions()[0].voters.len(), 2);
+    }
+
+    #[test]
+    fn most_votes_first_then_oldest() {
+        let mut queue = PartyQueue::default();
+        for id in ["a", "b", "c"] {
+            queue.suggest("ann".to_string(), track(id));
+        }
+        assert_eq!(names(&queue), ["a", "b", "c"]);
+
+        let c = queue.suggestions()[2].id;
+        queue.vote("bob".to_string(), c);
+        assert_eq!(names(&queue), ["c", "a", "b"]);
+
+        // A second vote from the same guest doesn't count
+        let b = queue.suggestions()[2].id;
+        queue.vote("ann".to_string(), b);
+        assert_eq!(names(&queue), ["c", "a", "b"]);
+
+        queue.vote("bob".to_string(), b);
+        assert_eq!(names(&queue), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn ignores_unknown_ids_and_rows() {
+        let mut queue = PartyQueue::default();
+        queue.suggest("ann".to_string(), track("a"));
+        queue.vote("bob".to_string(), 99);
+        assert_eq!(queue.suggestions()[0].voters.len(), 1);
+        assert!(queue.remove(1).is_none());
+        assert_eq!(queue.remove(0).map(|s| s.uri), Some("spotify:track:a".to_string()));
+        assert!(queue.is_empty());
+    }
+}
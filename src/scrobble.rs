@@ -0,0 +1,494 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use rspotify::model::{CurrentPlaybackContext, PlayableItem};
+use rspotify::prelude::Id;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use crate::action::Action;
+use crate::config::ScrobbleConfig;
+use crate::error::AppError;
+use crate::hooks;
+
+// A track counts as listened to after half its length or this long,
+// whichever comes first
+const MAX_LISTEN_THRESHOLD: Duration = Duration::from_secs(4 * 60);
+// ListenBrainz takes at most this many listens per request
+const MAX_LISTENS_PER_REQUEST: usize = 100;
+const FIRST_RETRY: Duration = Duration::from_secs(60);
+const MAX_RETRY: Duration = Duration::from_secs(30 * 60);
+
+/// Something to tell the scrobbling server about.
+#[derive(Debug, Clone)]
+pub enum Scrobble {
+    /// A track started; shown on the profile but not kept.
+    PlayingNow(Value),
+    /// A track was listened to long enough to count, as a listen with its
+    /// `listened_at` time.
+    Listened(Value),
+}
+
+/// Follows how long the item playing has actually played, seeks and
+/// pauses aside, to tell when it counts as a listen.
+#[derive(Debug, Default)]
+pub struct ListenTracker {
+    current: Option<Listening>,
+}
+
+#[derive(Debug)]
+struct Listening {
+    key: String,
+    /// `track_metadata` for the submission.
+    metadata: Value,
+    started_at: i64,
+    threshold: Duration,
+    listened: Duration,
+    /// When it last resumed, while it's playing.
+    playing_since: Option<Instant>,
+    last_progress_ms: i64,
+    counted: bool,
+}
+
+impl ListenTracker {
+    /// Brings the tracker up to date with what's playing. Called on every
+    /// tick and poll; returns what to submit, if anything.
+    pub fn update(
+        &mut self,
+        ctx: Option<&CurrentPlaybackContext>,
+        is_playing: bool,
+        progress_ms: i64,
+    ) -> Option<Scrobble> {
+        let key = ctx.and_then(hooks::item_key);
+        let same_item = self.current.as_ref().map(|current| &current.key) == key.as_ref();
+        // Repeating a track starts it over from near the beginning
+        let restarted = self.current.as_ref().is_some_and(|current| {
+            let rewound_ms = current.last_progress_ms - progress_ms;
+            current.counted && rewound_ms >= current.threshold.as_millis() as i64
+        });
+
+        if !same_item || restarted {
+            self.current = ctx.and_then(Listening::start);
+            let current = self.current.as_mut()?;
+            current.last_progress_ms = progress_ms;
+            if is_playing {
+                current.playing_since = Some(Instant::now());
+                return Some(Scrobble::PlayingNow(json!({ "track_metadata": current.metadata })));
+            }
+            return None;
+        }
+
+        let current = self.current.as_mut()?;
+        current.last_progress_ms = progress_ms;
+        match (current.playing_since, is_playing) {
+            (None, true) => current.playing_since = Some(Instant::now()),
+            (Some(since), false) => {
+                current.listened += since.elapsed();
+                current.playing_since = None;
+            }
+            _ => {}
+        }
+        let since_resumed = current.playing_since.map_or(Duration::ZERO, |t| t.elapsed());
+        let listened = current.listened + since_resumed;
+        if current.counted || listened < current.threshold {
+            return None;
+        }
+        current.counted = true;
+        Some(Scrobble::Listened(json!({
+            "listened_at": current.started_at,
+            "track_metadata": current.metadata,
+        })))
+    }
+}
+
+impl Listening {
+    /// Only music is scrobbled; podcasts aren't listens.
+    fn start(ctx: &CurrentPlaybackContext) -> Option<Self> {
+        let Some(PlayableItem::Track(track)) = &ctx.item else {
+            return None;
+        };
+        let key = hooks::item_key(ctx)?;
+        let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+        let mut additional_info = json!({
+            "duration_ms": track.duration.num_milliseconds(),
+            "artist_names": artists,
+            "music_service": "spotify.com",
+            "submission_client": "crabify",
+            "submission_client_version": env!("CARGO_PKG_VERSION"),
+        });
+        if let Some(id) = &track.id {
+            let url = format!("https://open.spotify.com/track/{}", id.id());
+            additional_info["spotify_id"] = url.clone().into();
+            additional_info["origin_url"] = url.into();
+        }
+        let duration = track.duration.to_std().unwrap_or_default();
+        Some(Self {
+            key,
+            metadata: json!({
+                "artist_name": artists.join(", "),
+                "track_name": track.name,
+                "release_name": track.album.name,
+                "additional_info": additional_info,
+            }),
+            started_at: chrono::Utc::now().timestamp(),
+            threshold: (duration / 2).min(MAX_LISTEN_THRESHOLD),
+            listened: Duration::ZERO,
+            playing_since: None,
+            last_progress_ms: 0,
+            counted: false,
+        })
+    }
+}
+
+/// Submits scrobbles from `scrobbles` until the app exits. Listens that
+/// can't be sent yet wait in a file at `queue_path`, so they survive
+/// restarts, and are retried with a growing delay. A token the server
+/// refuses is reported on `actions` once, and nothing more is sent until
+/// the next start; listens are still queued for then.
+pub async fn serve(
+    config: ScrobbleConfig,
+    queue_path: PathBuf,
+    mut scrobbles: mpsc::UnboundedReceiver<Scrobble>,
+    actions: mpsc::UnboundedSender<Action>,
+) -> Result<()> {
+    let client = Client::new(&config)?;
+    let mut queue = load_queue(&queue_path);
+    let mut retry = FIRST_RETRY;
+    let mut retry_at = (!queue.is_empty()).then(Instant::now);
+    let mut unauthorized = false;
+
+    loop {
+        let wait = retry_at.map(|at| at.saturating_duration_since(Instant::now()));
+        tokio::select! {
+            scrobble = scrobbles.recv() => match scrobble {
+                Some(Scrobble::PlayingNow(listen)) => {
+                    // Only matters while it's true, so it's never retried
+                    if !unauthorized {
+                        let _ = client.submit("playing_now", &[listen]).await;
+                    }
+                    continue;
+                }
+                Some(Scrobble::Listened(listen)) => {
+                    queue.push(listen);
+                    save_queue(&queue_path, &queue);
+                    if unauthorized {
+                        continue;
+                    }
+                }
+                None => return Ok(()),
+            },
+            _ = tokio::time::sleep(wait.unwrap_or_default()), if wait.is_some() => {}
+        }
+
+        // Send everything waiting, oldest first
+        while !queue.is_empty() {
+            let batch = queue.len().min(MAX_LISTENS_PER_REQUEST);
+            let listen_type = if batch == 1 { "single" } else { "import" };
+            match client.submit(listen_type, &queue[..batch]).await {
+                Ok(()) | Err(Failure::Rejected) => {
+                    // A listen the server won't take now never will be
+                    queue.drain(..batch);
+                    save_queue(&queue_path, &queue);
+                    retry = FIRST_RETRY;
+                    retry_at = None;
+                }
+                Err(Failure::Later) => {
+                    retry_at = Some(Instant::now() + retry);
+                    retry = (retry * 2).min(MAX_RETRY);
+                    break;
+                }
+                Err(Failure::Unauthorized) => {
+                    unauthorized = true;
+                    retry_at = None;
+                    let _ = actions.send(Action::Error {
+                        context: "Scrobbling stopped".to_string(),
+                        error: AppError::Other(anyhow::anyhow!(
+                            "ListenBrainz refused the token; check `token` under [scrobble]"
+                        )),
+                    });
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Why a submission failed.
+enum Failure {
+    /// Bad data; the listens should be dropped.
+    Rejected,
+    /// Offline, a server error or rate limited; worth trying again.
+    Later,
+    /// A bad token, which won't get better by itself.
+    Unauthorized,
+}
+
+/// A ListenBrainz API client.
+struct Client {
+    http: reqwest::Client,
+    url: String,
+    token: String,
+}
+
+impl Client {
+    fn new(config: &ScrobbleConfig) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .context("Could not set up the scrobbling client")?;
+        Ok(Self {
+            http,
+            url: format!("{}/1/submit-listens", config.url.trim_end_matches('/')),
+            token: config.token.trim().to_string(),
+        })
+    }
+
+    async fn submit(&self, listen_type: &str, listens: &[Value]) -> Result<(), Failure> {
+        let body = json!({ "listen_type": listen_type, "payload": listens });
+        let response = self
+            .http
+            .post(&self.url)
+            .header("Authorization", format!("Token {}", self.token))
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|_| Failure::Later)?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status.as_u16() == 401 {
+            Err(Failure::Unauthorized)
+        } else if status.is_client_error() && status.as_u16() != 429 {
+            Err(Failure::Rejected)
+        } else {
+            Err(Failure::Later)
+        }
+    }
+}
+
+fn load_queue(path: &PathBuf) -> Vec<Value> {
+    let data = std::fs::read_to_string(path).unwrap_or_default();
+    serde_json::from_str(&data).unwrap_or_default()
+}
+
+fn save_queue(path: &PathBuf, queue: &[Value]) {
+    if queue.is_empty() {
+        let _ = std::fs::remove_file(path);
+        return;
+    }
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let _ = std::fs::write(path, Value::from(queue).to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn playback(id: &str, duration_ms: i64) -> CurrentPlaybackContext {
+        serde_json::from_value(json!({
+            "device": {
+                "id": "device",
+                "is_active": true,
+                "is_private_session": false,
+                "is_restricted": false,
+                "name": "Desk",
+                "type": "Computer",
+                "volume_percent": 50,
+            },
+            "repeat_state": "off",
+            "shuffle_state": false,
+            "context": null,
+            "timestamp": 0,
+            "progress_ms": 0,
+            "is_playing": true,
+            "currently_playing_type": "track",
+            "actions": { "disallows": {} },
+            "item": {
+                "type": "track",
+                "id": id,
+                "uri": format!("spotify:track:{}", id),
+                "name": "Song",
+                "duration_ms": duration_ms,
+                "artists": [{ "name": "Artist", "external_urls": {} }],
+                "album": {
+                    "name": "Album",
+                    "artists": [],
+                    "images": [],
+                    "external_urls": {},
+                    "album_type": "album",
+                },
+                "available_markets": [],
+                "disc_number": 1,
+                "explicit": false,
+                "external_ids": {},
+                "external_urls": {},
+                "is_local": false,
+                "popularity": 0,
+                "track_number": 1,
+                "href": null,
+                "preview_url": null,
+            },
+        }))
+        .expect("valid playback")
+    }
+
+    #[test]
+    fn threshold_is_half_the_track_up_to_four_minutes() {
+        let threshold = |ms| Listening::start(&playback("a", ms)).unwrap().threshold;
+        assert_eq!(threshold(6 * 60 * 1000), Duration::from_secs(3 * 60));
+        assert_eq!(threshold(10 * 60 * 1000), MAX_LISTEN_THRESHOLD);
+    }
+
+    #[test]
+    fn counts_a_listen_once_past_the_threshold() {
+        let ctx = playback("a", 400);
+        let mut tracker = ListenTracker::default();
+        let started = tracker.update(Some(&ctx), true, 0);
+        assert!(matches!(started, Some(Scrobble::PlayingNow(_))));
+        assert!(tracker.update(Some(&ctx), true, 50).is_none());
+
+        std::thread::sleep(Duration::from_millis(250));
+        let Some(Scrobble::Listened(listen)) = tracker.update(Some(&ctx), true, 250) else {
+            panic!("expected a listen");
+        };
+        assert_eq!(listen["track_metadata"]["track_name"], "Song");
+        assert!(listen["listened_at"].is_i64());
+        assert!(tracker.update(Some(&ctx), true, 300).is_none());
+
+        // Starting over counts again
+        let restarted = tracker.update(Some(&ctx), true, 0);
+        assert!(matches!(restarted, Some(Scrobble::PlayingNow(_))));
+    }
+
+    #[test]
+    fn paused_time_doesnt_count() {
+        let ctx = playback("a", 400);
+        let mut tracker = ListenTracker::default();
+        assert!(tracker.update(Some(&ctx), false, 0).is_none());
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(tracker.update(Some(&ctx), false, 0).is_none());
+        assert!(tracker.update(Some(&ctx), true, 0).is_none());
+    }
+
+    /// A ListenBrainz stand-in answering with `statuses` in turn, then 200,
+    /// and passing on each request's token and body.
+    fn stub(statuses: &[u16]) -> (String, mpsc::UnboundedReceiver<(String, Value)>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").expect("stub server");
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let statuses = Arc::new(Mutex::new(statuses.to_vec()));
+        let (tx, rx) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let token = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Authorization"))
+                    .map(|h| h.value.to_string())
+                    .unwrap_or_default();
+                let mut body = String::new();
+                let _ = request.as_reader().read_to_string(&mut body);
+                let _ = tx.send((token, serde_json::from_str(&body).unwrap_or_default()));
+                let mut statuses = statuses.lock().unwrap();
+                let status = if statuses.is_empty() { 200 } else { statuses.remove(0) };
+                let _ = request.respond(tiny_http::Response::empty(status));
+            }
+        });
+        (url, rx)
+    }
+
+    fn config(url: String) -> ScrobbleConfig {
+        ScrobbleConfig {
+            enabled: true,
+            token: "secret".to_string(),
+            url,
+        }
+    }
+
+    fn queue_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("crabify-test-{}", std::process::id()));
+        dir.join(format!("{}.json", name))
+    }
+
+    async fn next<T>(rx: &mut mpsc::UnboundedReceiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("in time")
+            .expect("not closed")
+    }
+
+    fn listen(at: i64) -> Scrobble {
+        Scrobble::Listened(json!({ "listened_at": at, "track_metadata": {} }))
+    }
+
+    #[tokio::test]
+    async fn submits_listens() {
+        let (url, mut requests) = stub(&[]);
+        let path = queue_path("submits");
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (actions, _) = mpsc::unbounded_channel();
+        let scrobbler = tokio::spawn(serve(config(url), path.clone(), rx, actions));
+
+        tx.send(listen(1)).unwrap();
+        let (token, body) = next(&mut requests).await;
+        assert_eq!(token, "Token secret");
+        assert_eq!(body["listen_type"], "single");
+        assert_eq!(body["payload"][0]["listened_at"], 1);
+
+        drop(tx);
+        scrobbler.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn keeps_failed_listens_for_the_next_start() {
+        let (url, mut requests) = stub(&[503]);
+        let path = queue_path("retries");
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (actions, _) = mpsc::unbounded_channel();
+        let scrobbler = tokio::spawn(serve(config(url.clone()), path.clone(), rx, actions));
+
+        tx.send(listen(1)).unwrap();
+        next(&mut requests).await;
+        drop(tx);
+        scrobbler.await.unwrap().unwrap();
+        assert_eq!(load_queue(&path).len(), 1);
+
+        // Sent straight away on the next start
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (actions, _) = mpsc::unbounded_channel();
+        let scrobbler = tokio::spawn(serve(config(url), path.clone(), rx, actions));
+        let (_, body) = next(&mut requests).await;
+        assert_eq!(body["payload"][0]["listened_at"], 1);
+        drop(tx);
+        scrobbler.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn reports_a_refused_token_once() {
+        let (url, mut requests) = stub(&[401]);
+        let path = queue_path("unauthorized");
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (actions, mut reported) = mpsc::unbounded_channel();
+        let scrobbler = tokio::spawn(serve(config(url), path.clone(), rx, actions));
+
+        tx.send(listen(1)).unwrap();
+        next(&mut requests).await;
+        assert!(matches!(next(&mut reported).await, Action::Error { .. }));
+
+        tx.send(listen(2)).unwrap();
+        tx.send(Scrobble::PlayingNow(json!({ "track_metadata": {} }))).unwrap();
+        drop(tx);
+        scrobbler.await.unwrap().unwrap();
+        assert!(requests.try_recv().is_err());
+        assert!(reported.try_recv().is_err());
+        assert_eq!(load_queue(&path).len(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+}
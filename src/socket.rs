@@ -0,0 +1,227 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use rspotify::model::RepeatState;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, watch};
+
+use crate::action::{Event, Remote};
+
+/// A command sent to a running instance, one JSON object per line, e.g.
+/// `{"command": "volume", "volume": 40}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    Play,
+    Pause,
+    Toggle,
+    Next,
+    Prev,
+    Volume { volume: u8 },
+    ChangeVolume { by: i32 },
+    Seek { position_ms: i64 },
+    Shuffle { state: bool },
+    Repeat { state: RepeatState },
+    Like,
+    Unlike,
+    ToggleLike,
+    /// Replies with the current status.
+    Status,
+    /// Replies with the current status, then sends a line every time the
+    /// item, play state, volume, shuffle, repeat mode or like changes.
+    Subscribe,
+}
+
+impl Request {
+    fn remote(&self) -> Option<Remote> {
+        let remote = match *self {
+            Request::Play => Remote::Play,
+            Request::Pause => Remote::Pause,
+            Request::Toggle => Remote::PlayPause,
+            Request::Next => Remote::Next,
+            Request::Prev => Remote::Previous,
+            Request::Volume { volume } => Remote::SetVolume(volume),
+            Request::ChangeVolume { by } => Remote::ChangeVolume(by),
+            Request::Seek { position_ms } => Remote::SetPosition(position_ms),
+            Request::Shuffle { state } => Remote::SetShuffle(state),
+            Request::Repeat { state } => Remote::SetRepeat(state),
+            Request::Like => Remote::SetLiked(true),
+            Request::Unlike => Remote::SetLiked(false),
+            Request::ToggleLike => Remote::ToggleLiked,
+            Request::Status | Request::Subscribe => return None,
+        };
+        Some(remote)
+    }
+}
+
+/// Listens on `path` until the app exits. Commands go to the main loop
+/// through `events`; `status` holds the latest status as JSON.
+pub async fn serve(
+    path: PathBuf,
+    events: mpsc::UnboundedSender<Event>,
+    status: watch::Receiver<Value>,
+) -> Result<()> {
+    // A socket nobody answers on is left over from a crash
+    if UnixStream::connect(&path).await.is_ok() {
+        bail!("another crabify is already listening on {}", path.display());
+    }
+    let _ = std::fs::remove_file(&path);
+    // Made private before binding, so nobody else can reach the socket
+    // while it still has the umask's permissions
+    if let Some(dir) = path.parent() {
+        create_private_dir(dir)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    let _cleanup = RemoveOnDrop(path.clone());
+    set_owner_only(&path)?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let events = events.clone();
+        let status = status.clone();
+        tokio::spawn(async move {
+            let _ = handle_connection(stream, events, status).await;
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    events: mpsc::UnboundedSender<Event>,
+    mut status: watch::Receiver<Value>,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                write_line(&mut writer, &json!({ "ok": false, "error": e.to_string() })).await?;
+                continue;
+            }
+        };
+        match request {
+            Request::Status => {
+                let status = status.borrow().clone();
+                write_line(&mut writer, &json!({ "ok": true, "status": status })).await?;
+            }
+            Request::Subscribe => {
+                let mut last = status.borrow_and_update().clone();
+                write_line(&mut writer, &json!({ "ok": true, "status": last })).await?;
+                // Progress moves on every tick; only announce real changes
+                while status.changed().await.is_ok() {
+                    let next = status.borrow_and_update().clone();
+                    if without_progress(&next) != without_progress(&last) {
+                        write_line(&mut writer, &json!({ "event": "status", "status": next }))
+                            .await?;
+                    }
+                    last = next;
+                }
+                return Ok(());
+            }
+            request => {
+                // Queued for the app; failures show up in the TUI like any other
+                if let Some(remote) = request.remote() {
+                    events.send(Event::Remote(remote))?;
+                }
+                write_line(&mut writer, &json!({ "ok": true })).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A `subscribe` connection to a running instance.
+pub struct Subscription {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    _writer: OwnedWriteHalf,
+}
+
+impl Subscription {
+    /// Subscribes to status changes, or returns `None` if no instance is
+    /// listening on `path`.
+    pub async fn connect(path: &Path) -> Result<Option<Self>> {
+        let Ok(stream) = UnixStream::connect(path).await else {
+            return Ok(None);
+        };
+        let (reader, mut writer) = stream.into_split();
+        write_line(&mut writer, &serde_json::to_value(Request::Subscribe)?).await?;
+        Ok(Some(Self {
+            lines: BufReader::new(reader).lines(),
+            _writer: writer,
+        }))
+    }
+
+    /// The current status first, then each change; `None` once the
+    /// instance quits.
+    pub async fn next(&mut self) -> Result<Option<Value>> {
+        let Some(line) = self.lines.next_line().await? else {
+            return Ok(None);
+        };
+        let mut message: Value = serde_json::from_str(&line)?;
+        Ok(Some(message["status"].take()))
+    }
+}
+
+/// Sends `request` to a running instance and returns its reply, or `None`
+/// if none is listening on `path`.
+pub async fn request(path: &Path, request: &Request) -> Result<Option<Value>> {
+    let Ok(stream) = UnixStream::connect(path).await else {
+        return Ok(None);
+    };
+    let (reader, mut writer) = stream.into_split();
+    write_line(&mut writer, &serde_json::to_value(request)?).await?;
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .context("crabify closed the connection without replying")?;
+    let reply: Value = serde_json::from_str(&line)?;
+    if reply["ok"] != true {
+        bail!("{}", reply["error"].as_str().unwrap_or("request failed"));
+    }
+    Ok(Some(reply))
+}
+
+async fn write_line(writer: &mut (impl AsyncWriteExt + Unpin), value: &Value) -> Result<()> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(())
+}
+
+fn without_progress(status: &Value) -> Value {
+    let mut status = status.clone();
+    if let Some(status) = status.as_object_mut() {
+        status.remove("progress_ms");
+    }
+    status
+}
+
+fn create_private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    Ok(())
+}
+
+fn set_owner_only(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(())
+}
+
+struct RemoveOnDrop(PathBuf);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
@@ -1,52 +1,104 @@
-use anyhow::Result;
+use std::future::Future;
+
 use rspotify::model::{
-    CurrentPlaybackContext, Device, FullTrack, Market, SavedTrack, SearchType,
+    CurrentPlaybackContext, Device, FullTrack, Market, RepeatState, SavedTrack, SearchType,
     SimplifiedPlaylist, PlayableItem, PlaylistId, TrackId,
 };
 use rspotify::prelude::*;
-use rspotify::AuthCodePkceSpotify;
+use rspotify::{AuthCodePkceSpotify, ClientResult};
+
+use crate::auth::{self, LoginFlow};
+use crate::error::{AppError, Result};
 
+// Clones share the token, so a refresh by one is seen by all
+#[derive(Clone)]
 pub struct SpotifyClient {
     client: AuthCodePkceSpotify,
+    login_flow: LoginFlow,
+    market: Market,
+}
+
+/// Awaits an rspotify call, classifying any failure into an `AppError`.
+async fn api<T>(request: impl Future<Output = ClientResult<T>>) -> Result<T> {
+    match request.await {
+        Ok(value) => Ok(value),
+        Err(e) => Err(AppError::from_client_error(e).await),
+    }
 }
 
 impl SpotifyClient {
-    pub fn new(client: AuthCodePkceSpotify) -> Self {
-        Self { client }
+    pub fn new(client: AuthCodePkceSpotify, login_flow: LoginFlow, market: Market) -> Self {
+        Self {
+            client,
+            login_flow,
+            market,
+        }
+    }
+
+    /// Exchanges the refresh token for a new access token.
+    pub async fn refresh_token(&self) -> Result<()> {
+        api(self.client.refresh_token()).await
+    }
+
+    /// Runs the browser login flow again, replacing the shared token. The
+    /// login URL goes to `show_url`, as the TUI owns the terminal.
+    pub async fn reauthenticate(&self, show_url: impl FnOnce(String)) -> Result<()> {
+        // The headless flow needs the terminal, which the TUI owns
+        if self.login_flow == LoginFlow::Headless {
+            return Err(AppError::Auth(
+                "session expired. Restart crabify to log in again.".to_string(),
+            ));
+        }
+        let mut client = self.client.clone();
+        auth::authorize_in_app(&mut client, show_url)
+            .await
+            .map_err(|e| AppError::Auth(e.to_string()))
     }
 
     pub async fn fetch_now_playing(&self) -> Result<Option<CurrentPlaybackContext>> {
-        let market = Some(Market::FromToken);
-        let result = self
-            .client
-            .current_playback(market, None::<Vec<_>>)
-            .await?;
+        let market = Some(self.market);
+        let result = api(self.client.current_playback(market, None::<Vec<_>>)).await?;
         Ok(result)
     }
 
-    pub async fn play_pause(&self, is_playing: bool) -> Result<()> {
-        if is_playing {
-            self.client.pause_playback(None).await?;
-        } else {
-            self.client.resume_playback(None, None).await?;
-        }
+    pub async fn pause(&self) -> Result<()> {
+        api(self.client.pause_playback(None)).await?;
+        Ok(())
+    }
+
+    pub async fn resume(&self) -> Result<()> {
+        api(self.client.resume_playback(None, None)).await?;
         Ok(())
     }
 
     pub async fn next_track(&self) -> Result<()> {
-        self.client.next_track(None).await?;
+        api(self.client.next_track(None)).await?;
         Ok(())
     }
 
     pub async fn previous_track(&self) -> Result<()> {
-        self.client.previous_track(None).await?;
+        api(self.client.previous_track(None)).await?;
         Ok(())
     }
 
     pub async fn set_volume(&self, volume_percent: u8) -> Result<()> {
-        self.client
-            .volume(volume_percent, None)
-            .await?;
+        api(self.client.volume(volume_percent, None)).await?;
+        Ok(())
+    }
+
+    pub async fn seek(&self, position_ms: i64) -> Result<()> {
+        let position = chrono::Duration::milliseconds(position_ms);
+        api(self.client.seek_track(position, None)).await?;
+        Ok(())
+    }
+
+    pub async fn set_shuffle(&self, state: bool) -> Result<()> {
+        api(self.client.shuffle(state, None)).await?;
+        Ok(())
+    }
+
+    pub async fn set_repeat(&self, state: RepeatState) -> Result<()> {
+        api(self.client.repeat(state, None)).await?;
         Ok(())
     }
 
@@ -55,10 +107,11 @@ impl SpotifyClient {
         let mut offset = 0;
         let limit = 50;
         loop {
-            let page = self
-                .client
-                .current_user_playlists_manual(Some(limit), Some(offset))
-                .await?;
+            let page = api(
+                self.client
+                    .current_user_playlists_manual(Some(limit), Some(offset)),
+            )
+            .await?;
             let total = page.total;
             playlists.extend(page.items);
             offset += limit;
@@ -75,10 +128,14 @@ impl SpotifyClient {
         let mut offset = 0;
         let limit = 100;
         loop {
-            let page = self
-                .client
-                .playlist_items_manual(playlist_id.as_ref(), None, None, Some(limit), Some(offset))
-                .await?;
+            let page = api(self.client.playlist_items_manual(
+                playlist_id.as_ref(),
+                None,
+                Some(self.market),
+                Some(limit),
+                Some(offset),
+            ))
+            .await?;
             let total = page.total;
             for item in page.items {
                 if let Some(PlayableItem::Track(track)) = item.track {
@@ -108,33 +165,54 @@ impl SpotifyClient {
         } else if context_uri.contains("artist") {
             PlayContextId::Artist(rspotify::model::ArtistId::from_id_or_uri(context_uri)?.into_static())
         } else {
-            anyhow::bail!("Unsupported context URI: {}", context_uri);
+            return Err(AppError::Api(format!(
+                "Unsupported context URI: {}",
+                context_uri
+            )));
         };
 
         let offset = Some(rspotify::model::Offset::Position(
             chrono::Duration::milliseconds(offset as i64),
         ));
 
-        self.client
-            .start_context_playback(context_id, None, offset, None)
-            .await?;
+        api(self.client.start_context_playback(context_id, None, offset, None)).await?;
         Ok(())
     }
 
     pub async fn play_track(&self, track_uri: &str) -> Result<()> {
         let track_id = TrackId::from_id_or_uri(track_uri)?;
         let uris = [PlayableId::Track(track_id)];
-        self.client
-            .start_uris_playback(uris, None, None, None)
-            .await?;
+        api(self.client.start_uris_playback(uris, None, None, None)).await?;
         Ok(())
     }
 
+    pub async fn fetch_track(&self, track_uri: &str) -> Result<FullTrack> {
+        let track_id = TrackId::from_id_or_uri(track_uri)?;
+        api(self.client.track(track_id, Some(self.market))).await
+    }
+
+    pub async fn add_to_queue(&self, track_uri: &str) -> Result<()> {
+        let track_id = TrackId::from_id_or_uri(track_uri)?;
+        api(self.client.add_item_to_queue(PlayableId::Track(track_id), None)).await?;
+        Ok(())
+    }
+
+    /// The item playing and what Spotify will play after it.
+    pub async fn fetch_queue(&self) -> Result<(Option<PlayableItem>, Vec<PlayableItem>)> {
+        let queue = api(self.client.current_user_queue()).await?;
+        Ok((queue.currently_playing, queue.queue))
+    }
+
     pub async fn search_tracks(&self, query: &str) -> Result<Vec<FullTrack>> {
-        let result = self
-            .client
-            .search(query, SearchType::Track, None, None, Some(20), Some(0))
-            .await?;
+        let result = api(self.client.search(
+            query,
+            SearchType::Track,
+            Some(self.market),
+            None,
+            Some(20),
+            Some(0),
+        ))
+        .await?;
 
         let mut tracks = Vec::new();
         if let rspotify::model::SearchResult::Tracks(page) = result {
@@ -148,10 +226,11 @@ impl SpotifyClient {
         let mut offset = 0;
         let limit = 50;
         loop {
-            let page = self
-                .client
-                .current_user_saved_tracks_manual(None, Some(limit), Some(offset))
-                .await?;
+            let page = api(
+                self.client
+                    .current_user_saved_tracks_manual(Some(self.market), Some(limit), Some(offset)),
+            )
+            .await?;
             let total = page.total;
             songs.extend(page.items);
             offset += limit;
@@ -162,33 +241,40 @@ impl SpotifyClient {
         Ok(songs)
     }
 
+    /// Appends a track to a playlist, returning the playlist's new snapshot ID.
+    pub async fn add_to_playlist(&self, playlist_id: &str, track_id: &str) -> Result<String> {
+        let playlist_id = PlaylistId::from_id_or_uri(playlist_id)?;
+        let track_id = TrackId::from_id_or_uri(track_id)?;
+        let items = [PlayableId::Track(track_id)];
+        let result = api(self.client.playlist_add_items(playlist_id, items, None)).await?;
+        Ok(result.snapshot_id)
+    }
+
     pub async fn save_track(&self, track_id: &str) -> Result<()> {
         let track_id = TrackId::from_id_or_uri(track_id)?;
-        self.client
-            .current_user_saved_tracks_add([track_id])
-            .await?;
+        api(self.client.current_user_saved_tracks_add([track_id])).await?;
         Ok(())
     }
 
     pub async fn remove_track(&self, track_id: &str) -> Result<()> {
         let track_id = TrackId::from_id_or_uri(track_id)?;
-        self.client
-            .current_user_saved_tracks_delete([track_id])
-            .await?;
+        api(self.client.current_user_saved_tracks_delete([track_id])).await?;
         Ok(())
     }
 
-    pub async fn check_saved_tracks(&self, track_ids: &[String]) -> Result<Vec<bool>> {
-        let ids: Vec<TrackId> = track_ids
-            .iter()
-            .filter_map(|id| TrackId::from_id_or_uri(id).ok())
-            .collect();
-        let result = self.client.current_user_saved_tracks_contains(ids).await?;
-        Ok(result)
+    pub async fn is_saved(&self, track_id: &str) -> Result<bool> {
+        let track_id = TrackId::from_id_or_uri(track_id)?;
+        let saved = api(self.client.current_user_saved_tracks_contains([track_id])).await?;
+        Ok(saved.first().copied().unwrap_or(false))
     }
 
     pub async fn fetch_devices(&self) -> Result<Vec<Device>> {
-        let devices = self.client.device().await?;
+        let devices = api(self.client.device()).await?;
         Ok(devices)
     }
+
+    pub async fn transfer_playback(&self, device_id: &str) -> Result<()> {
+        api(self.client.transfer_playback(device_id, Some(true))).await?;
+        Ok(())
+    }
 }
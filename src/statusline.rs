@@ -0,0 +1,242 @@
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::app::format_duration;
+use crate::auth;
+use crate::cli;
+use crate::config::AppConfig;
+use crate::error::AppError;
+#[cfg(unix)]
+use crate::socket::Subscription;
+use crate::spotify::SpotifyClient;
+
+/// The fields a statusline template can use, as `{name}`.
+pub const FIELDS: &[&str] = &[
+    "icon", "state", "title", "artist", "album", "duration", "volume", "device", "heart", "liked",
+    "shuffle", "repeat",
+];
+
+/// The first `{field}` in `template` that isn't one of [`FIELDS`].
+pub fn unknown_field(template: &str) -> Option<&str> {
+    fields(template).find(|field| !FIELDS.contains(field))
+}
+
+fn fields(template: &str) -> impl Iterator<Item = &str> {
+    template.split('{').skip(1).filter_map(|rest| rest.split_once('}').map(|(field, _)| field))
+}
+
+/// Fills in `template` from a status as `status --json` prints it, in one
+/// pass so a title containing `{artist}` stays as it is.
+pub fn render(template: &str, status: &Value) -> String {
+    let mut line = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        line.push_str(&rest[..start]);
+        let field = rest[start + 1..].split_once('}').map(|(field, _)| field);
+        match field.filter(|field| FIELDS.contains(field)) {
+            Some(field) => {
+                line.push_str(&field_value(field, status));
+                rest = &rest[start + field.len() + 2..];
+            }
+            None => {
+                line.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    line.push_str(rest);
+    line.trim().to_string()
+}
+
+/// The text for one template field.
+pub fn field_value(field: &str, status: &Value) -> String {
+    let item = &status["item"];
+    let playing = status["is_playing"] == true;
+    let liked = status["liked"] == true;
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+    match field {
+        "icon" => if playing { "▶" } else { "⏸" }.to_string(),
+        "state" => if playing { "playing" } else { "paused" }.to_string(),
+        "title" => text(&item["name"]),
+        "artist" => match item["artists"].as_array() {
+            Some(artists) => {
+                let artists: Vec<&str> = artists.iter().filter_map(Value::as_str).collect();
+                artists.join(", ")
+            }
+            None => text(&item["show"]),
+        },
+        "album" => text(&item["album"]),
+        "duration" => format_duration(item["duration_ms"].as_i64().unwrap_or(0)),
+        "volume" => status["device"]["volume_percent"]
+            .as_u64()
+            .map(|v| v.to_string())
+            .unwrap_or_default(),
+        "device" => text(&status["device"]["name"]),
+        "heart" => if liked { "♥" } else { "♡" }.to_string(),
+        "liked" => if liked { "♥" } else { "" }.to_string(),
+        "shuffle" => if status["shuffle"] == true { "on" } else { "off" }.to_string(),
+        "repeat" => text(&status["repeat"]),
+        _ => String::new(),
+    }
+}
+
+/// The line for `status` in the bar's format: plain text, or the JSON
+/// waybar's `custom` module reads with `"return-type": "json"`.
+fn output(config: &AppConfig, template: &str, waybar: bool, status: &Value) -> String {
+    let idle = status["item"].is_null();
+    let text = if idle {
+        render(&config.statusline.idle, status)
+    } else {
+        render(template, status)
+    };
+    if !waybar {
+        return text;
+    }
+    let state = match (idle, status["is_playing"] == true) {
+        (true, _) => "stopped",
+        (false, true) => "playing",
+        (false, false) => "paused",
+    };
+    let mut class = vec![state];
+    if status["liked"] == true {
+        class.push("liked");
+    }
+    let tooltip = if idle {
+        String::new()
+    } else {
+        render("{title}\n{artist}\n{album}", status)
+    };
+    // waybar reads both as Pango markup
+    json!({
+        "text": escape_markup(&text),
+        "tooltip": escape_markup(&tooltip),
+        "alt": state,
+        "class": class,
+    })
+    .to_string()
+}
+
+pub fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Prints a line whenever what `template` shows changes, until the bar
+/// closes the pipe. Follows a running TUI over its control socket when
+/// there is one, and polls the API otherwise.
+pub async fn run(config: &AppConfig, headless: bool, waybar: bool, template: &str) -> Result<()> {
+    let mut last = None;
+    let mut print = |status: &Value| -> Result<()> {
+        let line = output(config, template, waybar, status);
+        if last.as_ref() != Some(&line) {
+            writeln!(std::io::stdout(), "{}", line)?;
+            last = Some(line);
+        }
+        Ok(())
+    };
+    let mut client = None;
+    let interval = Duration::from_secs(config.poll_interval_secs);
+
+    loop {
+        #[cfg(unix)]
+        if let Some(mut subscription) = Subscription::connect(&config.socket_path()?).await? {
+            while let Some(status) = subscription.next().await? {
+                print(&status)?;
+            }
+            continue;
+        }
+
+        let client = match &client {
+            Some(client) => client,
+            None => {
+                let (spotify_auth, login_flow) = auth::authenticate(config, headless).await?;
+                client.insert(SpotifyClient::new(spotify_auth, login_flow, config.market()))
+            }
+        };
+        let wait = match cli::fetch_status(client).await {
+            Ok(status) => {
+                print(&status)?;
+                interval
+            }
+            Err(e) => {
+                // Keep the last line up; the bar shouldn't flicker on a blip
+                eprintln!("crabify: {}", e);
+                match e.downcast_ref::<AppError>() {
+                    Some(AppError::RateLimited {
+                        retry_after: Some(secs),
+                    }) => interval.max(Duration::from_secs(*secs)),
+                    _ => interval,
+                }
+            }
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status() -> Value {
+        json!({
+            "is_playing": true,
+            "item": {
+                "name": "Song {artist}",
+                "artists": ["A", "B & C"],
+                "album": "Album",
+                "duration_ms": 185_000,
+            },
+            "shuffle": true,
+            "repeat": "context",
+            "liked": true,
+            "device": { "name": "Desk", "volume_percent": 40 },
+        })
+    }
+
+    #[test]
+    fn fills_in_fields_once() {
+        let line = render("{icon} {artist} - {title} ({duration}) {heart}", &status());
+        assert_eq!(line, "▶ A, B & C - Song {artist} (3:05) ♥");
+        let line = render("{state} {volume}% on {device}, {shuffle}/{repeat}", &status());
+        assert_eq!(line, "playing 40% on Desk, on/context");
+    }
+
+    #[test]
+    fn leaves_unknown_fields_and_stray_braces() {
+        assert_eq!(render("{nope} {title", &status()), "{nope} {title");
+        assert_eq!(unknown_field("{title} {nope} {x}"), Some("nope"));
+        assert_eq!(unknown_field("{title} }{"), None);
+    }
+
+    #[test]
+    fn trims_empty_fields() {
+        let mut paused = status();
+        paused["is_playing"] = false.into();
+        paused["liked"] = false.into();
+        assert_eq!(render("{liked} {title} {icon}", &paused), "Song {artist} ⏸");
+    }
+
+    #[test]
+    fn waybar_output_is_escaped_json() {
+        let config = AppConfig::default();
+        let line = output(&config, "{artist}", true, &status());
+        let line: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(line["text"], "A, B &amp; C");
+        assert_eq!(line["alt"], "playing");
+        assert_eq!(line["class"], json!(["playing", "liked"]));
+        assert_eq!(line["tooltip"], "Song {artist}\nA, B &amp; C\nAlbum");
+    }
+
+    #[test]
+    fn uses_the_idle_template_with_nothing_playing() {
+        let mut config = AppConfig::default();
+        config.statusline.idle = "nothing".to_string();
+        let idle = json!({ "is_playing": false, "item": null });
+        assert_eq!(output(&config, "{title}", false, &idle), "nothing");
+        let line: Value = serde_json::from_str(&output(&config, "{title}", true, &idle)).unwrap();
+        assert_eq!(line["alt"], "stopped");
+        assert_eq!(line["tooltip"], "");
+    }
+}
@@ -0,0 +1,74 @@
+use ratatui::layout::Rect;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+use crate::app::App;
+use crate::keymap::Command;
+use crate::ui::layout;
+
+pub fn render(f: &mut Frame, app: &App) {
+    let theme = &app.settings.theme;
+    let popup_area = popup_area(app, f.area());
+
+    f.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.border_active)
+        .title(layout::title_with_keys(
+            app,
+            "Devices",
+            &[(Command::Select, "transfer"), (Command::Back, "close")],
+        ));
+
+    if app.devices.is_empty() {
+        let empty = Paragraph::new("No devices found. Open Spotify on a device.")
+            .style(theme.muted)
+            .block(block);
+        f.render_widget(empty, popup_area);
+        return;
+    }
+
+    let items: Vec<ListItem> = app
+        .devices
+        .iter()
+        .enumerate()
+        .map(|(i, device)| {
+            let marker = if device.is_active { "▶" } else { " " };
+            let volume = device
+                .volume_percent
+                .map(|v| format!("  {}%", v))
+                .unwrap_or_default();
+            let style = if i == app.device_index {
+                theme.highlight
+            } else {
+                theme.text
+            };
+            ListItem::new(format!(
+                "{} {} ({:?}){}",
+                marker, device.name, device._type, volume
+            ))
+            .style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(block);
+
+    let mut state = ListState::default();
+    state.select(Some(app.device_index));
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+pub fn popup_area(app: &App, area: Rect) -> Rect {
+    let popup_width = 50u16.min(area.width.saturating_sub(4));
+    let popup_height = (app.devices.len() as u16 + 2)
+        .clamp(5, 15)
+        .min(area.height.saturating_sub(4));
+
+    Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width,
+        height: popup_height,
+    }
+}
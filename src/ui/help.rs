@@ -1,65 +1,63 @@
 use ratatui::layout::Rect;
-use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState};
 use ratatui::Frame;
 
-pub fn render(f: &mut Frame) {
-    let area = f.area();
+use crate::app::App;
+use crate::keymap::Command;
 
-    let popup_width = 50u16.min(area.width.saturating_sub(4));
-    let popup_height = 20u16.min(area.height.saturating_sub(4));
-
-    let popup_area = Rect {
-        x: (area.width.saturating_sub(popup_width)) / 2,
-        y: (area.height.saturating_sub(popup_height)) / 2,
-        width: popup_width,
-        height: popup_height,
-    };
+pub fn render(f: &mut Frame, app: &App) {
+    let theme = &app.settings.theme;
+    let bindings = app.settings.keymap.help_entries();
+    let popup_area = popup_area(app, f.area());
 
     f.render_widget(Clear, popup_area);
 
-    let bindings = vec![
-        ("q", "Quit"),
-        ("Tab / Shift+Tab", "Switch screen"),
-        ("j / ↓", "Move down"),
-        ("k / ↑", "Move up"),
-        ("Enter", "Select / Play"),
-        ("Tab (in Library)", "Switch panel"),
-        ("/", "Start search"),
-        ("Esc", "Exit search / Close help"),
-        ("Space", "Play / Pause"),
-        ("n", "Next track"),
-        ("p", "Previous track"),
-        ("+", "Volume up"),
-        ("-", "Volume down"),
-        ("s", "Toggle like (selected)"),
-        ("l", "Toggle like (now playing)"),
-        ("?", "Toggle help"),
-    ];
-
-    let lines: Vec<Line> = bindings
+    let items: Vec<ListItem> = bindings
         .iter()
         .map(|(key, desc)| {
-            Line::from(vec![
-                Span::styled(
-                    format!("{:>20}", key),
-                    Style::default()
-                        .fg(Color::Green)
-                        .add_modifier(Modifier::BOLD),
-                ),
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:>19}", key), theme.highlight),
                 Span::raw("  "),
-                Span::styled(*desc, Style::default().fg(Color::White)),
-            ])
+                Span::styled(*desc, theme.text),
+            ]))
         })
         .collect();
 
-    let help = Paragraph::new(lines).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Green))
-            .title(" Keybindings (? to close) "),
-    );
+    // Say where we are when not everything fits
+    let fits = bindings.len() <= popup_area.height.saturating_sub(2) as usize;
+    let position = match fits {
+        true => String::new(),
+        false => format!("{}/{}, ", app.help_index + 1, bindings.len()),
+    };
+    let title = match app.settings.keymap.keys_for(Command::Help).first() {
+        Some(key) => format!(" Keybindings ({}{} to close) ", position, key),
+        None => " Keybindings ".to_string(),
+    };
+    let help = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.border_active)
+                .title(title),
+        )
+        .highlight_symbol("▶")
+        .highlight_spacing(HighlightSpacing::Always);
+
+    let mut state = ListState::default();
+    state.select(Some(app.help_index));
+    f.render_stateful_widget(help, popup_area, &mut state);
+}
+
+pub fn popup_area(app: &App, area: Rect) -> Rect {
+    let rows = app.settings.keymap.help_entries().len() as u16;
+    let popup_width = 50u16.min(area.width.saturating_sub(4));
+    let popup_height = (rows + 2).min(area.height.saturating_sub(4));
 
-    f.render_widget(help, popup_area);
+    Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width,
+        height: popup_height,
+    }
 }
@@ -0,0 +1,154 @@
+use ratatui::layout::{Margin, Position, Rect};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::{App, Panel, Screen};
+use crate::ui::{devices, layout, now_playing, party, profiles, search, stats};
+
+/// Something on screen that reacts to the mouse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Tab(Screen),
+    /// A row in the current screen's list or table, and which library panel
+    /// it is in.
+    Row(Panel, usize),
+    SearchInput,
+    /// A point on the progress bar, from 0.0 to 1.0.
+    ProgressBar(f64),
+    Device(usize),
+    Profile(usize),
+    Suggestion(usize),
+}
+
+/// Finds what's at `position`, mirroring the layout `ui::render` draws into
+/// `area`.
+pub fn target_at(app: &App, area: Rect, position: Position) -> Option<Target> {
+    // Popups swallow clicks on whatever is underneath them
+    if app.show_help || app.palette.is_some() {
+        return None;
+    }
+    if app.show_profiles {
+        let rows = profiles::popup_area(app, area).inner(Margin::new(1, 1));
+        let offset = popup_offset(rows, app.profile_index);
+        return row_at(rows, offset, app.profiles.len(), position).map(Target::Profile);
+    }
+    if app.show_party {
+        let rows = party::popup_area(app, area).inner(Margin::new(1, 1));
+        let offset = popup_offset(rows, app.party_index);
+        let len = app.party.suggestions().len();
+        return row_at(rows, offset, len, position).map(Target::Suggestion);
+    }
+    if app.show_devices {
+        let rows = devices::popup_area(app, area).inner(Margin::new(1, 1));
+        let offset = popup_offset(rows, app.device_index);
+        return row_at(rows, offset, app.devices.len(), position).map(Target::Device);
+    }
+
+    let chunks = layout::main_layout(area);
+    if chunks[0].contains(position) {
+        return tab_at(chunks[0], position).map(Target::Tab);
+    }
+    if chunks[2].contains(position) {
+        let bar = now_playing::progress_bar_area(chunks[2]);
+        if app.now_playing.is_none() || !bar.contains(position) {
+            return None;
+        }
+        let fraction = (position.x - bar.x) as f64 / bar.width.max(1) as f64;
+        return Some(Target::ProgressBar(fraction));
+    }
+
+    match app.screen {
+        Screen::Library => {
+            let playlists = list_rows(Screen::Library, Panel::Left, area);
+            let (offset, len) = (app.playlist_offset, app.playlists.len());
+            if let Some(i) = row_at(playlists, offset, len, position) {
+                return Some(Target::Row(Panel::Left, i));
+            }
+            let tracks = list_rows(Screen::Library, Panel::Right, area);
+            row_at(tracks, app.track_offset, app.playlist_tracks.len(), position)
+                .map(|i| Target::Row(Panel::Right, i))
+        }
+        Screen::Search => {
+            let body = layout::main_layout(area)[1];
+            if search::search_layout(body)[0].contains(position) {
+                return Some(Target::SearchInput);
+            }
+            let results = list_rows(Screen::Search, Panel::Left, area);
+            row_at(results, app.search_offset, app.search_results.len(), position)
+                .map(|i| Target::Row(Panel::Left, i))
+        }
+        Screen::LikedSongs => {
+            let rows = list_rows(Screen::LikedSongs, Panel::Left, area);
+            row_at(rows, app.liked_offset, app.liked_songs.len(), position)
+                .map(|i| Target::Row(Panel::Left, i))
+        }
+        Screen::Stats => {
+            let rows = list_rows(Screen::Stats, Panel::Left, area);
+            row_at(rows, app.stats_offset, app.top_tracks().len(), position)
+                .map(|i| Target::Row(Panel::Left, i))
+        }
+    }
+}
+
+/// Where a screen's list (the given panel's, in the library) draws its rows
+/// when the whole UI is drawn into `area`.
+pub fn list_rows(screen: Screen, panel: Panel, area: Rect) -> Rect {
+    let body = layout::main_layout(area)[1];
+    match (screen, panel) {
+        (Screen::Library, Panel::Left) => layout::body_split(body)[0].inner(Margin::new(1, 1)),
+        (Screen::Library, Panel::Right) => table_rows(layout::body_split(body)[1]),
+        (Screen::Search, _) => table_rows(search::search_layout(body)[1]),
+        (Screen::LikedSongs, _) => table_rows(body),
+        (Screen::Stats, _) => table_rows(stats::stats_layout(body)[1]),
+    }
+}
+
+/// Which library panel `position` is over, for scrolling.
+pub fn panel_at(area: Rect, position: Position) -> Option<Panel> {
+    let body = layout::main_layout(area)[1];
+    let halves = layout::body_split(body);
+    if halves[0].contains(position) {
+        Some(Panel::Left)
+    } else if halves[1].contains(position) {
+        Some(Panel::Right)
+    } else {
+        None
+    }
+}
+
+fn tab_at(area: Rect, position: Position) -> Option<Screen> {
+    // Each title is padded by a space on both sides and followed by " | "
+    let mut x = area.x + 1;
+    for screen in Screen::all() {
+        let width = screen.label().width() as u16 + 2;
+        if (x..x + width).contains(&position.x) {
+            return Some(*screen);
+        }
+        x += width + 3;
+    }
+    None
+}
+
+/// The area a bordered table draws its rows in, below the header.
+fn table_rows(area: Rect) -> Rect {
+    let inner = area.inner(Margin::new(1, 1));
+    Rect {
+        y: inner.y + 2,
+        height: inner.height.saturating_sub(2),
+        ..inner
+    }
+}
+
+/// Maps `position` to a row index, given the first row on screen.
+fn row_at(rows: Rect, offset: usize, len: usize, position: Position) -> Option<usize> {
+    if !rows.contains(position) {
+        return None;
+    }
+    let index = offset + (position.y - rows.y) as usize;
+    (index < len).then_some(index)
+}
+
+/// Popups are rendered with a fresh list state each frame, so they scroll
+/// just far enough to show the selected row at the bottom.
+fn popup_offset(rows: Rect, selected: usize) -> usize {
+    selected.saturating_sub((rows.height as usize).saturating_sub(1))
+}
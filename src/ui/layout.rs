@@ -1,10 +1,29 @@
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Clear, Paragraph, Tabs};
 use ratatui::Frame;
 
 use crate::app::{App, Screen};
+use crate::config::DEFAULT_PROFILE;
+use crate::keymap::Command;
+use crate::ui::theme::Theme;
+
+/// A title with the keys currently bound to each action, like
+/// ` Devices (enter to transfer, esc to close) `. Unbound actions are
+/// left out.
+pub fn title_with_keys(app: &App, title: &str, actions: &[(Command, &str)]) -> String {
+    let hints: Vec<String> = actions
+        .iter()
+        .filter_map(|(command, action)| {
+            let keys = app.settings.keymap.keys_for(*command);
+            keys.first().map(|key| format!("{} to {}", key, action))
+        })
+        .collect();
+    match hints.is_empty() {
+        true => format!(" {} ", title),
+        false => format!(" {} ({}) ", title, hints.join(", ")),
+    }
+}
 
 pub fn main_layout(area: Rect) -> Vec<Rect> {
     Layout::default()
@@ -12,7 +31,7 @@ pub fn main_layout(area: Rect) -> Vec<Rect> {
         .constraints([
             Constraint::Length(3), // Header (tabs)
             Constraint::Min(8),   // Body
-            Constraint::Length(3), // Footer (now playing)
+            Constraint::Length(4), // Footer (now playing)
         ])
         .split(area)
         .to_vec()
@@ -30,15 +49,14 @@ pub fn body_split(area: Rect) -> Vec<Rect> {
 }
 
 pub fn render_tabs(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.settings.theme;
     let titles: Vec<Line> = Screen::all()
         .iter()
         .map(|s| {
             let style = if *s == app.screen {
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD)
+                theme.highlight
             } else {
-                Style::default().fg(Color::DarkGray)
+                theme.muted
             };
             Line::from(Span::styled(s.label(), style))
         })
@@ -49,24 +67,22 @@ pub fn render_tabs(f: &mut Frame, app: &App, area: Rect) {
         .position(|s| *s == app.screen)
         .unwrap_or(0);
 
+    let title = if app.profile == DEFAULT_PROFILE {
+        " 🦀 crabify ".to_string()
+    } else {
+        format!(" 🦀 crabify ({}) ", app.profile)
+    };
+
     let tabs = Tabs::new(titles)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(" 🦀 crabify "),
-        )
+        .block(Block::default().borders(Borders::ALL).title(title))
         .select(selected)
-        .highlight_style(
-            Style::default()
-                .fg(Color::Green)
-                .add_modifier(Modifier::BOLD),
-        )
+        .highlight_style(theme.highlight)
         .divider(Span::raw(" | "));
 
     f.render_widget(tabs, area);
 }
 
-pub fn render_flash(f: &mut Frame, msg: &str) {
+pub fn render_flash(f: &mut Frame, msg: &str, is_error: bool, theme: &Theme) {
     let area = f.area();
     let popup_width = (msg.len() as u16 + 4).min(area.width - 4);
     let popup_area = Rect {
@@ -77,13 +93,18 @@ pub fn render_flash(f: &mut Frame, msg: &str) {
     };
 
     f.render_widget(Clear, popup_area);
+    let (style, border_style, title) = if is_error {
+        (theme.error, theme.error, " Error ")
+    } else {
+        (theme.text, theme.border_active, " crabify ")
+    };
     let paragraph = Paragraph::new(msg.to_string())
-        .style(Style::default().fg(Color::Red))
+        .style(style)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Red))
-                .title(" Error "),
+                .border_style(border_style)
+                .title(title),
         );
     f.render_widget(paragraph, popup_area);
 }
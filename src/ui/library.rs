@@ -1,10 +1,10 @@
-use ratatui::layout::Constraint;
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::style::Style;
 use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Row, Table, TableState};
 use ratatui::Frame;
 use ratatui::layout::Rect;
 
 use crate::app::{App, Panel};
+use crate::ui::track_table;
 use crate::ui::layout::body_split;
 
 pub fn render(f: &mut Frame, app: &App, area: Rect) {
@@ -15,12 +15,9 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
 }
 
 fn render_playlists(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.settings.theme;
     let is_active = app.active_panel == Panel::Left;
-    let border_style = if is_active {
-        Style::default().fg(Color::Green)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
+    let border_style = theme.border_for(is_active);
 
     let items: Vec<ListItem> = app
         .playlists
@@ -28,11 +25,9 @@ fn render_playlists(f: &mut Frame, app: &App, area: Rect) {
         .enumerate()
         .map(|(i, playlist)| {
             let style = if i == app.playlist_index && is_active {
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD)
+                theme.highlight
             } else {
-                Style::default().fg(Color::White)
+                theme.text
             };
             ListItem::new(playlist.name.as_str()).style(style)
         })
@@ -45,18 +40,15 @@ fn render_playlists(f: &mut Frame, app: &App, area: Rect) {
             .title(" Playlists "),
     );
 
-    let mut state = ListState::default();
+    let mut state = ListState::default().with_offset(app.playlist_offset);
     state.select(Some(app.playlist_index));
     f.render_stateful_widget(list, area, &mut state);
 }
 
 fn render_tracks(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.settings.theme;
     let is_active = app.active_panel == Panel::Right;
-    let border_style = if is_active {
-        Style::default().fg(Color::Green)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
+    let border_style = theme.border_for(is_active);
 
     if app.loading && app.playlist_tracks.is_empty() {
         let block = Block::default()
@@ -64,58 +56,35 @@ fn render_tracks(f: &mut Frame, app: &App, area: Rect) {
             .border_style(border_style)
             .title(" Tracks ");
         let loading = ratatui::widgets::Paragraph::new("Loading...")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .block(block);
         f.render_widget(loading, area);
         return;
     }
 
-    let header = Row::new(vec!["#", "Title", "Artist", "Duration"])
-        .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
-        .bottom_margin(1);
+    let columns = &app.settings.columns.playlist;
+    let header = track_table::header(theme, columns);
 
     let rows: Vec<Row> = app
         .playlist_tracks
         .iter()
         .enumerate()
         .map(|(i, track)| {
-            let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
-            let duration_secs = track.duration.num_seconds();
-            let duration = format!("{}:{:02}", duration_secs / 60, duration_secs % 60);
-            let liked = if app.liked_track_ids.contains(
+            let liked = app.liked_track_ids.contains(
                 &track.id.as_ref().map(|id| id.to_string()).unwrap_or_default(),
-            ) {
-                "♥"
-            } else {
-                ""
-            };
+            );
             let style = if i == app.track_index && is_active {
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD)
+                theme.highlight
             } else {
                 Style::default()
             };
 
-            Row::new(vec![
-                format!("{} {}", i + 1, liked),
-                track.name.clone(),
-                artists.join(", "),
-                duration,
-            ])
-            .style(style)
+            let number = track_table::number(theme, i + 1, liked);
+            track_table::row(columns, number, track).style(style)
         })
         .collect();
 
-    let table = Table::new(
-        rows,
-        [
-            Constraint::Length(6),
-            Constraint::Percentage(45),
-            Constraint::Percentage(35),
-            Constraint::Length(8),
-        ],
-    )
+    let table = Table::new(rows, track_table::widths(columns))
     .header(header)
     .block(
         Block::default()
@@ -123,13 +92,9 @@ fn render_tracks(f: &mut Frame, app: &App, area: Rect) {
             .border_style(border_style)
             .title(" Tracks "),
     )
-    .row_highlight_style(
-        Style::default()
-            .fg(Color::Green)
-            .add_modifier(Modifier::BOLD),
-    );
+    .row_highlight_style(theme.highlight);
 
-    let mut state = TableState::default();
+    let mut state = TableState::default().with_offset(app.track_offset);
     state.select(Some(app.track_index));
     f.render_stateful_widget(table, area, &mut state);
 }
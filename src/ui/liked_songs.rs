@@ -1,18 +1,19 @@
-use ratatui::layout::Constraint;
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::style::Style;
 use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
 use ratatui::Frame;
 use ratatui::layout::Rect;
 
 use crate::app::App;
+use crate::ui::track_table;
 
 pub fn render(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.settings.theme;
     if app.loading && app.liked_songs.is_empty() {
         let block = Block::default()
             .borders(Borders::ALL)
             .title(" Liked Songs ");
         let loading = Paragraph::new("Loading...")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .block(block);
         f.render_widget(loading, area);
         return;
@@ -21,77 +22,45 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
     if app.liked_songs.is_empty() {
         let block = Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Green))
+            .border_style(theme.border_active)
             .title(" Liked Songs ");
         let empty = Paragraph::new("No liked songs found")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .block(block);
         f.render_widget(empty, area);
         return;
     }
 
-    let header = Row::new(vec!["#", "Title", "Artist", "Album", "Duration"])
-        .style(
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
-        )
-        .bottom_margin(1);
+    let columns = &app.settings.columns.liked_songs;
+    let header = track_table::header(theme, columns);
 
     let rows: Vec<Row> = app
         .liked_songs
         .iter()
         .enumerate()
         .map(|(i, saved)| {
-            let track = &saved.track;
-            let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
-            let album = track.album.name.as_str();
-            let duration_secs = track.duration.num_seconds();
-            let duration = format!("{}:{:02}", duration_secs / 60, duration_secs % 60);
-
             let style = if i == app.liked_index {
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD)
+                theme.highlight
             } else {
                 Style::default()
             };
 
-            Row::new(vec![
-                format!("♥ {}", i + 1),
-                track.name.clone(),
-                artists.join(", "),
-                album.to_string(),
-                duration,
-            ])
-            .style(style)
+            let number = track_table::number(theme, i + 1, true);
+            track_table::row(columns, number, &saved.track).style(style)
         })
         .collect();
 
-    let table = Table::new(
-        rows,
-        [
-            Constraint::Length(6),
-            Constraint::Percentage(30),
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
-            Constraint::Length(8),
-        ],
-    )
+    let table = Table::new(rows, track_table::widths(columns))
     .header(header)
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Green))
+            .border_style(theme.border_active)
             .title(" Liked Songs "),
     )
-    .row_highlight_style(
-        Style::default()
-            .fg(Color::Green)
-            .add_modifier(Modifier::BOLD),
-    );
+    .row_highlight_style(theme.highlight);
 
-    let mut state = TableState::default();
+    let mut state = TableState::default().with_offset(app.liked_offset);
     state.select(Some(app.liked_index));
     f.render_stateful_widget(table, area, &mut state);
 }
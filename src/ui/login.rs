@@ -0,0 +1,44 @@
+use ratatui::layout::Rect;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+
+use crate::app::App;
+use crate::keymap::Command;
+use crate::ui::layout;
+
+/// The URL of a browser login that's waiting, in case the browser didn't
+/// open or opened on another screen.
+pub fn render(f: &mut Frame, app: &App) {
+    let Some(ref url) = app.login_url else {
+        return;
+    };
+    let theme = &app.settings.theme;
+    let area = f.area();
+
+    let popup_width = 70u16.min(area.width.saturating_sub(4));
+    let text = format!("Log in to Spotify in your browser, or open this URL:\n\n{}", url);
+    // Rough line count once wrapped, plus the borders
+    let inner_width = popup_width.saturating_sub(2).max(1) as usize;
+    let lines = 2 + url.len().div_ceil(inner_width);
+    let popup_height = (lines as u16 + 2).min(area.height.saturating_sub(4));
+    let popup_area = Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    f.render_widget(Clear, popup_area);
+
+    let title = layout::title_with_keys(app, "Spotify login", &[(Command::Back, "hide")]);
+    let paragraph = Paragraph::new(text)
+        .style(theme.text)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.border_active)
+                .title(title),
+        );
+    f.render_widget(paragraph, popup_area);
+}
@@ -1,9 +1,18 @@
 pub mod layout;
 pub mod library;
+pub mod login;
 pub mod search;
 pub mod now_playing;
+pub mod palette;
 pub mod liked_songs;
 pub mod help;
+pub mod hit_test;
+pub mod devices;
+pub mod profiles;
+pub mod party;
+pub mod stats;
+pub mod theme;
+pub mod track_table;
 
 use ratatui::Frame;
 
@@ -20,6 +29,7 @@ pub fn render(f: &mut Frame, app: &App) {
         Screen::Library => library::render(f, app, chunks[1]),
         Screen::Search => search::render(f, app, chunks[1]),
         Screen::LikedSongs => liked_songs::render(f, app, chunks[1]),
+        Screen::Stats => stats::render(f, app, chunks[1]),
     }
 
     // Footer (now playing)
@@ -27,11 +37,36 @@ pub fn render(f: &mut Frame, app: &App) {
 
     // Flash message overlay
     if let Some((ref msg, _)) = app.flash_message {
-        layout::render_flash(f, msg);
+        layout::render_flash(f, msg, app.flash_is_error, &app.settings.theme);
+    }
+
+    // Device picker overlay
+    if app.show_devices {
+        devices::render(f, app);
+    }
+
+    // Party queue overlay
+    if app.show_party {
+        party::render(f, app);
+    }
+
+    // Account picker overlay
+    if app.show_profiles {
+        profiles::render(f, app);
+    }
+
+    // Browser login waiting on the user
+    if app.login_url.is_some() {
+        login::render(f, app);
     }
 
     // Help overlay
     if app.show_help {
-        help::render(f);
+        help::render(f, app);
+    }
+
+    // Command palette
+    if app.palette.is_some() {
+        palette::render(f, app);
     }
 }
@@ -1,19 +1,22 @@
 use ratatui::layout::Rect;
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::style::Modifier;
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Paragraph};
 use ratatui::Frame;
 
+use rspotify::model::RepeatState;
+
 use crate::app::App;
 
 pub fn render(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.settings.theme;
     let block = Block::default()
         .borders(Borders::ALL)
         .title(" Now Playing ");
 
     if app.now_playing.is_none() {
         let empty = Paragraph::new("No playback detected. Start playing on a Spotify client.")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .block(block);
         f.render_widget(empty, area);
         return;
@@ -21,6 +24,7 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
 
     let inner = block.inner(area);
     f.render_widget(block, area);
+    let info_area = Rect { height: 1, ..inner };
 
     let track_name = app.current_track_name().unwrap_or_default();
     let progress_text = app.progress_text();
@@ -32,28 +36,54 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
         .unwrap_or(false);
     let heart = if is_liked { "♥" } else { "♡" };
 
-    let line = Line::from(vec![
-        Span::styled(
-            format!(" {} ", play_icon),
-            Style::default()
-                .fg(Color::Green)
-                .add_modifier(Modifier::BOLD),
-        ),
+    let mut modes = Vec::new();
+    if let Some(ref ctx) = app.now_playing {
+        if ctx.shuffle_state {
+            modes.push("Shuffle");
+        }
+        match ctx.repeat_state {
+            RepeatState::Context => modes.push("Repeat"),
+            RepeatState::Track => modes.push("Repeat one"),
+            RepeatState::Off => {}
+        }
+    }
+
+    let mut line = Line::from(vec![
+        Span::styled(format!(" {} ", play_icon), theme.highlight),
         Span::styled(
             format!("{} ", heart),
-            Style::default().fg(if is_liked { Color::Red } else { Color::DarkGray }),
-        ),
-        Span::styled(
-            track_name,
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
+            if is_liked { theme.heart } else { theme.muted },
         ),
+        Span::styled(track_name, theme.text.add_modifier(Modifier::BOLD)),
         Span::raw("  "),
-        Span::styled(progress_text, Style::default().fg(Color::DarkGray)),
+        Span::styled(progress_text, theme.muted),
         Span::raw("  "),
-        Span::styled(volume_str, Style::default().fg(Color::DarkGray)),
+        Span::styled(volume_str, theme.muted),
     ]);
+    if !modes.is_empty() {
+        line.push_span(Span::raw("  "));
+        line.push_span(Span::styled(modes.join(" · "), theme.muted));
+    }
+
+    f.render_widget(Paragraph::new(line), info_area);
 
-    f.render_widget(Paragraph::new(line), inner);
+    // Progress bar, clickable to seek
+    let bar = progress_bar_area(area);
+    let filled = (app.progress_fraction() * bar.width as f64).round() as usize;
+    let empty = (bar.width as usize).saturating_sub(filled);
+    let line = Line::from(vec![
+        Span::styled("━".repeat(filled), theme.highlight),
+        Span::styled("─".repeat(empty), theme.muted),
+    ]);
+    f.render_widget(Paragraph::new(line), bar);
+}
+
+/// Where the progress bar sits within the now playing footer.
+pub fn progress_bar_area(area: Rect) -> Rect {
+    Rect {
+        x: area.x + 2,
+        y: area.y + 2,
+        width: area.width.saturating_sub(4),
+        height: area.height.saturating_sub(3).min(1),
+    }
 }
@@ -0,0 +1,94 @@
+use ratatui::layout::Rect;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::App;
+
+pub fn render(f: &mut Frame, app: &App) {
+    let Some(ref palette) = app.palette else {
+        return;
+    };
+    let theme = &app.settings.theme;
+    let area = f.area();
+
+    let items = app.palette_items();
+    let matches = app.palette_matches();
+    // Free-text prompts, and pickers with nothing to pick yet, have no list
+    let list_height = if items.is_empty() { 0 } else { matches.len().clamp(1, 12) as u16 };
+
+    let popup_width = 60u16.min(area.width.saturating_sub(4));
+    let popup_height = (list_height + 4).min(area.height.saturating_sub(2));
+    let popup_area = Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: area.height / 5,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    f.render_widget(Clear, popup_area);
+
+    let title = palette
+        .prompt
+        .as_ref()
+        .map(|prompt| prompt.title)
+        .unwrap_or(" Commands ");
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.border_active)
+        .title(title);
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    // Input line, then a blank line, then the matches
+    let input_area = Rect { height: 1, ..inner };
+    let input = Line::from(vec![
+        Span::styled(": ", theme.highlight),
+        Span::styled(palette.input.as_str(), theme.text),
+    ]);
+    f.render_widget(Paragraph::new(input), input_area);
+    f.set_cursor_position((
+        input_area.x + 2 + palette.input.width() as u16,
+        input_area.y,
+    ));
+
+    if items.is_empty() {
+        return;
+    }
+    let list_area = Rect {
+        y: inner.y + 2,
+        height: inner.height.saturating_sub(2),
+        ..inner
+    };
+
+    if matches.is_empty() {
+        f.render_widget(Paragraph::new("No matches").style(theme.muted), list_area);
+        return;
+    }
+
+    let width = list_area.width as usize;
+    let rows: Vec<ListItem> = matches
+        .iter()
+        .enumerate()
+        .map(|(i, index)| {
+            let (label, keys) = &items[*index];
+            let style = if i == palette.selected {
+                theme.highlight
+            } else {
+                theme.text
+            };
+            let gap = width.saturating_sub(label.width() + keys.width() + 1).max(1);
+            ListItem::new(Line::from(vec![
+                Span::styled(label.clone(), style),
+                Span::raw(" ".repeat(gap)),
+                Span::styled(keys.clone(), theme.muted),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(palette.selected));
+    f.render_stateful_widget(List::new(rows), list_area, &mut state);
+}
+
@@ -0,0 +1,78 @@
+use ratatui::layout::Rect;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+use crate::app::App;
+use crate::keymap::Command;
+use crate::ui::layout;
+
+pub fn render(f: &mut Frame, app: &App) {
+    let theme = &app.settings.theme;
+    let popup_area = popup_area(app, f.area());
+
+    f.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.border_active)
+        .title(layout::title_with_keys(
+            app,
+            "Party queue",
+            &[
+                (Command::Select, "queue now"),
+                (Command::Remove, "remove"),
+                (Command::Back, "close"),
+            ],
+        ));
+
+    if app.party.is_empty() {
+        let empty = Paragraph::new("No suggestions yet. Guests suggest tracks on the web remote.")
+            .style(theme.muted)
+            .block(block);
+        f.render_widget(empty, popup_area);
+        return;
+    }
+
+    let items: Vec<ListItem> = app
+        .party
+        .suggestions()
+        .iter()
+        .enumerate()
+        .map(|(i, suggestion)| {
+            let track = &suggestion.track;
+            let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+            let style = if i == app.party_index {
+                theme.highlight
+            } else {
+                theme.text
+            };
+            ListItem::new(format!(
+                "{:>3} ▲ {} - {}",
+                suggestion.voters.len(),
+                track.name,
+                artists.join(", ")
+            ))
+            .style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(block);
+
+    let mut state = ListState::default();
+    state.select(Some(app.party_index));
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+pub fn popup_area(app: &App, area: Rect) -> Rect {
+    let popup_width = 70u16.min(area.width.saturating_sub(4));
+    let popup_height = (app.party.suggestions().len() as u16 + 2)
+        .clamp(5, 20)
+        .min(area.height.saturating_sub(4));
+
+    Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width,
+        height: popup_height,
+    }
+}
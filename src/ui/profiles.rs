@@ -0,0 +1,58 @@
+use ratatui::layout::Rect;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState};
+use ratatui::Frame;
+
+use crate::app::App;
+use crate::keymap::Command;
+use crate::ui::layout;
+
+pub fn render(f: &mut Frame, app: &App) {
+    let theme = &app.settings.theme;
+    let popup_area = popup_area(app, f.area());
+
+    f.render_widget(Clear, popup_area);
+
+    let items: Vec<ListItem> = app
+        .profiles
+        .iter()
+        .enumerate()
+        .map(|(i, profile)| {
+            let marker = if *profile == app.profile { "▶" } else { " " };
+            let style = if i == app.profile_index {
+                theme.highlight
+            } else {
+                theme.text
+            };
+            ListItem::new(format!("{} {}", marker, profile)).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border_active)
+            .title(layout::title_with_keys(
+                app,
+                "Accounts",
+                &[(Command::Select, "switch"), (Command::Back, "close")],
+            )),
+    );
+
+    let mut state = ListState::default();
+    state.select(Some(app.profile_index));
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+pub fn popup_area(app: &App, area: Rect) -> Rect {
+    let popup_width = 40u16.min(area.width.saturating_sub(4));
+    let popup_height = (app.profiles.len() as u16 + 2)
+        .clamp(3, 15)
+        .min(area.height.saturating_sub(4));
+
+    Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width,
+        height: popup_height,
+    }
+}
@@ -0,0 +1,185 @@
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
+use ratatui::Frame;
+
+use crate::app::App;
+use crate::history::{self, Period, Ranked, Stats};
+use crate::keymap::Command;
+use crate::ui::layout;
+
+pub fn render(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.settings.theme;
+    let chunks = stats_layout(area);
+    render_summary(f, app, chunks[0]);
+
+    let message = if !app.settings.history {
+        Some("Listening history is off; turn it on under [history] in the config")
+    } else {
+        match &app.stats {
+            None => Some("Loading..."),
+            Some(stats) if stats.plays == 0 => Some("Nothing played in this period yet"),
+            Some(_) => None,
+        }
+    };
+    let (Some(stats), None) = (&app.stats, message) else {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border_active)
+            .title(" Top Tracks ");
+        let empty = Paragraph::new(message.unwrap_or_default()).style(theme.muted).block(block);
+        f.render_widget(empty, chunks[1]);
+        return;
+    };
+
+    render_top_tracks(f, app, stats, chunks[1]);
+    render_top(f, app, " Top Artists ", "Artist", &stats.artists, chunks[2]);
+    render_top(f, app, " Top Albums ", "Album", &stats.albums, chunks[3]);
+}
+
+/// The summary line, top tracks, top artists and top albums.
+pub fn stats_layout(area: Rect) -> Vec<Rect> {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Period and totals
+            Constraint::Min(5),   // Top lists
+        ])
+        .split(area);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(rows[1]);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(columns[1]);
+    vec![rows[0], columns[0], right[0], right[1]]
+}
+
+fn render_summary(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.settings.theme;
+    let mut spans = Vec::new();
+    for period in Period::all() {
+        let style = if *period == app.stats_period {
+            theme.highlight
+        } else {
+            theme.muted
+        };
+        spans.push(Span::styled(format!(" {} ", period.label()), style));
+    }
+    if let Some(stats) = app.stats.as_ref().filter(|stats| stats.plays > 0) {
+        spans.push(Span::styled(summary(stats), theme.text));
+    }
+
+    let paragraph = Paragraph::new(Line::from(spans)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border)
+            .title(layout::title_with_keys(
+                app,
+                "Stats",
+                &[(Command::Stats, "change period")],
+            )),
+    );
+    f.render_widget(paragraph, area);
+}
+
+fn summary(stats: &Stats) -> String {
+    format!(
+        "   {} listened · {} plays · {} skipped",
+        history::format_listened(stats.listened_ms),
+        stats.plays,
+        history::skip_rate(stats.skips, stats.plays)
+    )
+}
+
+fn render_top_tracks(f: &mut Frame, app: &App, stats: &Stats, area: Rect) {
+    let theme = &app.settings.theme;
+    let header = Row::new(["#", "Title", "Artist", "Time", "Plays", "Skipped"])
+        .style(theme.header)
+        .bottom_margin(1);
+    let rows: Vec<Row> = stats
+        .tracks
+        .iter()
+        .enumerate()
+        .map(|(i, track)| {
+            let style = if i == app.stats_index {
+                theme.highlight
+            } else {
+                Style::default()
+            };
+            Row::new([
+                (i + 1).to_string(),
+                track.name.clone(),
+                track.detail.clone(),
+                history::format_listened(track.listened_ms),
+                track.plays.to_string(),
+                history::skip_rate(track.skips, track.plays),
+            ])
+            .style(style)
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Fill(3),
+        Constraint::Fill(2),
+        Constraint::Length(8),
+        Constraint::Length(6),
+        Constraint::Length(8),
+    ];
+
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.border_active)
+                .title(" Top Tracks "),
+        )
+        .row_highlight_style(theme.highlight);
+
+    let mut state = TableState::default().with_offset(app.stats_offset);
+    state.select(Some(app.stats_index));
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+/// Top artists or albums, which are only shown, not selected.
+fn render_top(f: &mut Frame, app: &App, title: &str, column: &str, top: &[Ranked], area: Rect) {
+    let theme = &app.settings.theme;
+    let header = Row::new(["#", column, "Time", "Plays"])
+        .style(theme.header)
+        .bottom_margin(1);
+    let rows: Vec<Row> = top
+        .iter()
+        .enumerate()
+        .map(|(i, ranked)| {
+            let name = if ranked.detail.is_empty() {
+                ranked.name.clone()
+            } else {
+                format!("{} — {}", ranked.name, ranked.detail)
+            };
+            Row::new([
+                (i + 1).to_string(),
+                name,
+                history::format_listened(ranked.listened_ms),
+                ranked.plays.to_string(),
+            ])
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Fill(1),
+        Constraint::Length(8),
+        Constraint::Length(6),
+    ];
+
+    let table = Table::new(rows, widths).header(header).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border)
+            .title(title),
+    );
+    f.render_widget(table, area);
+}
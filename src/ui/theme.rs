@@ -0,0 +1,122 @@
+use ratatui::style::{Color, Modifier, Style};
+
+pub const NAMES: &[&str] = &["default", "high-contrast", "light", "monochrome"];
+
+/// Styles for each role in the UI, so screens never pick colors themselves.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// Borders of inactive panels.
+    pub border: Style,
+    /// Borders of the focused panel and of popups.
+    pub border_active: Style,
+    pub text: Style,
+    /// Secondary text: placeholders, progress, inactive tabs.
+    pub muted: Style,
+    /// The selected row, tab or key.
+    pub highlight: Style,
+    /// Table headers.
+    pub header: Style,
+    pub heart: Style,
+    pub error: Style,
+}
+
+impl Theme {
+    /// Looks up a theme by name. Setting `NO_COLOR` forces `monochrome`.
+    pub fn load(name: &str) -> Theme {
+        if std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty()) {
+            return Self::monochrome();
+        }
+        Self::by_name(name).unwrap_or_else(Self::default_theme)
+    }
+
+    pub fn by_name(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Self::default_theme()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "light" => Some(Self::light()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    pub fn border_for(&self, active: bool) -> Style {
+        if active {
+            self.border_active
+        } else {
+            self.border
+        }
+    }
+
+    fn default_theme() -> Theme {
+        Theme {
+            border: Style::default().fg(Color::DarkGray),
+            border_active: Style::default().fg(Color::Green),
+            text: Style::default().fg(Color::White),
+            muted: Style::default().fg(Color::DarkGray),
+            highlight: Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+            header: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            heart: Style::default().fg(Color::Red),
+            error: Style::default().fg(Color::Red),
+        }
+    }
+
+    fn high_contrast() -> Theme {
+        Theme {
+            border: Style::default().fg(Color::White),
+            border_active: Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            text: Style::default().fg(Color::White),
+            muted: Style::default().fg(Color::Gray),
+            highlight: Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            header: Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            heart: Style::default().fg(Color::LightRed),
+            error: Style::default()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+        }
+    }
+
+    // For terminals with a light background, where white text disappears
+    fn light() -> Theme {
+        Theme {
+            border: Style::default().fg(Color::Gray),
+            border_active: Style::default().fg(Color::Blue),
+            text: Style::default().fg(Color::Black),
+            muted: Style::default().fg(Color::DarkGray),
+            highlight: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            header: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+            heart: Style::default().fg(Color::Red),
+            error: Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        }
+    }
+
+    // No colors at all; roles are told apart by weight and reverse video
+    fn monochrome() -> Theme {
+        Theme {
+            border: Style::default(),
+            border_active: Style::default().add_modifier(Modifier::BOLD),
+            text: Style::default(),
+            muted: Style::default().add_modifier(Modifier::DIM),
+            highlight: Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            header: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            heart: Style::default(),
+            error: Style::default().add_modifier(Modifier::BOLD),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use ratatui::layout::Constraint;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Cell, Row};
+use rspotify::model::FullTrack;
+
+use crate::config::Column;
+use crate::ui::theme::Theme;
+
+pub fn header(theme: &Theme, columns: &[Column]) -> Row<'static> {
+    let titles: Vec<&str> = columns
+        .iter()
+        .map(|column| match column {
+            Column::Number => "#",
+            Column::Title => "Title",
+            Column::Artist => "Artist",
+            Column::Album => "Album",
+            Column::Duration => "Duration",
+        })
+        .collect();
+    Row::new(titles).style(theme.header).bottom_margin(1)
+}
+
+/// The `#` cell: the row number, with a heart if the track is liked.
+pub fn number(theme: &Theme, n: usize, liked: bool) -> Line<'static> {
+    let mut spans = vec![Span::raw(format!("{} ", n))];
+    if liked {
+        spans.push(Span::styled("♥", theme.heart));
+    }
+    Line::from(spans)
+}
+
+/// Builds a row with the configured columns.
+pub fn row(columns: &[Column], number: Line<'static>, track: &FullTrack) -> Row<'static> {
+    let cells: Vec<Cell> = columns
+        .iter()
+        .map(|column| match column {
+            Column::Number => Cell::from(number.clone()),
+            Column::Title => Cell::from(track.name.clone()),
+            Column::Artist => {
+                let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+                Cell::from(artists.join(", "))
+            }
+            Column::Album => Cell::from(track.album.name.clone()),
+            Column::Duration => {
+                let duration_secs = track.duration.num_seconds();
+                Cell::from(format!("{}:{:02}", duration_secs / 60, duration_secs % 60))
+            }
+        })
+        .collect();
+    Row::new(cells)
+}
+
+pub fn widths(columns: &[Column]) -> Vec<Constraint> {
+    columns
+        .iter()
+        .map(|column| match column {
+            Column::Number => Constraint::Length(6),
+            Column::Title => Constraint::Fill(4),
+            Column::Artist => Constraint::Fill(3),
+            Column::Album => Constraint::Fill(3),
+            Column::Duration => Constraint::Length(8),
+        })
+        .collect()
+}